@@ -0,0 +1,70 @@
+# Deutsch
+
+## Menu
+continue = Weiterspielen
+play = Spielen
+try-again = Nochmal
+copy-result = Ergebnis kopieren
+daily = Tägliche Runde ({ $streak })
+daily-played = Täglich: gespielt ({ $streak })
+daily-share = Täglich: teilen ({ $streak })
+editor = Editor
+walls-on = Wände: an
+walls-off = Wände: aus
+language = Sprache: Deutsch
+settings = Einstellungen
+palette-standard = Farben: Standard
+palette-protanopia = Farben: Protanopie
+palette-deuteranopia = Farben: Deuteranopie
+palette-tritanopia = Farben: Tritanopie
+symbols-on = Symbole: an
+symbols-off = Symbole: aus
+game-speed = Spieltempo: { $percent } %
+turn-based-on = Rundenbasiert: an
+turn-based-off = Rundenbasiert: aus
+turn-buffer = Gemerkte Kurven: { $turns }
+drop-redundant-on = Doppelte Kurven: ignoriert
+drop-redundant-off = Doppelte Kurven: gemerkt
+gravity-down = Schwerkraft: unten
+gravity-up = Schwerkraft: oben
+gravity-left = Schwerkraft: links
+gravity-right = Schwerkraft: rechts
+gravity-center = Schwerkraft: zur Mitte
+difficulty-easy = Schwierigkeit: leicht
+difficulty-normal = Schwierigkeit: normal
+difficulty-hard = Schwierigkeit: schwer
+difficulty-expert = Schwierigkeit: Experte
+skin = Aussehen: { $name }
+visual-theme = Thema: { $name }
+race-ghost = Gegen deinen besten Lauf
+tail-safe-on = Schwanz: betretbar
+tail-safe-off = Schwanz: tödlich
+invulnerable-steps = Schonzeit nach Wachsen: { $steps }
+bite-off-on = Beißen: kürzt den Schwanz
+bite-off-off = Beißen: beendet den Lauf
+back = Zurück
+made-with-bevy = Mit Bevy gemacht
+open-source = Quelloffen
+
+## Modes
+mode-endless = Endlos
+mode-time-attack = Zeitrennen
+mode-zen = Zen
+no-high-scores = Noch keine Bestenliste
+best-length = Beste Länge: { $score }
+best-gems = Beste Edelsteine: { $score }
+
+## HUD
+snake-length = Schlangenlänge: { $length }
+record-length = Rekordlänge: { $length }
+gems-destroyed = Zerstörte Edelsteine: { $count }
+total-destroyed = Insgesamt zerstört: { $count }
+largest-chain = Längste Kette: { $count }
+next-growth = Wachstum
+time-left = Verbleibend: { $time }
+power-up-slow-time = Zeitlupe
+power-up-ghost = Geist
+power-up-detonate = Sprengung
+power-up-shrink = Schrumpfen
+cascade-count = Kette: { $count }
+cascade-skip = Beliebige Taste zum Überspringen
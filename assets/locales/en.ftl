@@ -0,0 +1,70 @@
+# English
+
+## Menu
+continue = Continue
+play = Play
+try-again = Try again
+copy-result = Copy result
+daily = Daily ({ $streak })
+daily-played = Daily: played ({ $streak })
+daily-share = Daily: share ({ $streak })
+editor = Editor
+walls-on = Walls: on
+walls-off = Walls: off
+language = Language: English
+settings = Settings
+palette-standard = Colours: standard
+palette-protanopia = Colours: protanopia
+palette-deuteranopia = Colours: deuteranopia
+palette-tritanopia = Colours: tritanopia
+symbols-on = Gem symbols: on
+symbols-off = Gem symbols: off
+game-speed = Game speed: { $percent }%
+turn-based-on = Turn based: on
+turn-based-off = Turn based: off
+turn-buffer = Queued turns: { $turns }
+drop-redundant-on = Repeated turns: ignored
+drop-redundant-off = Repeated turns: queued
+gravity-down = Gravity: down
+gravity-up = Gravity: up
+gravity-left = Gravity: left
+gravity-right = Gravity: right
+gravity-center = Gravity: to the middle
+difficulty-easy = Difficulty: easy
+difficulty-normal = Difficulty: normal
+difficulty-hard = Difficulty: hard
+difficulty-expert = Difficulty: expert
+skin = Skin: { $name }
+visual-theme = Theme: { $name }
+race-ghost = Race your best run
+tail-safe-on = Tail: safe to enter
+tail-safe-off = Tail: deadly
+invulnerable-steps = Grace after growing: { $steps }
+bite-off-on = Biting: cuts the tail
+bite-off-off = Biting: ends the run
+back = Back
+made-with-bevy = Made with Bevy
+open-source = Open source
+
+## Modes
+mode-endless = Endless
+mode-time-attack = Time Attack
+mode-zen = Zen
+no-high-scores = No high scores yet
+best-length = Best length: { $score }
+best-gems = Best gems: { $score }
+
+## HUD
+snake-length = Snake length: { $length }
+record-length = Record length: { $length }
+gems-destroyed = Gems destroyed: { $count }
+total-destroyed = Total destroyed: { $count }
+largest-chain = Largest chain: { $count }
+next-growth = Growth
+time-left = Time left: { $time }
+power-up-slow-time = Slow time
+power-up-ghost = Ghost
+power-up-detonate = Detonate
+power-up-shrink = Shrink
+cascade-count = Chain: { $count }
+cascade-skip = Press any key to skip
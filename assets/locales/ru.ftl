@@ -0,0 +1,70 @@
+# Русский
+
+## Menu
+continue = Продолжить
+play = Играть
+try-again = Ещё раз
+copy-result = Копировать результат
+daily = Ежедневная ({ $streak })
+daily-played = Ежедневная: сыграно ({ $streak })
+daily-share = Ежедневная: поделиться ({ $streak })
+editor = Редактор
+walls-on = Стены: вкл
+walls-off = Стены: выкл
+language = Язык: Русский
+settings = Настройки
+palette-standard = Цвета: обычные
+palette-protanopia = Цвета: протанопия
+palette-deuteranopia = Цвета: дейтеранопия
+palette-tritanopia = Цвета: тританопия
+symbols-on = Символы: вкл
+symbols-off = Символы: выкл
+game-speed = Скорость игры: { $percent }%
+turn-based-on = Пошаговый режим: вкл
+turn-based-off = Пошаговый режим: выкл
+turn-buffer = Очередь поворотов: { $turns }
+drop-redundant-on = Повторные повороты: пропуск
+drop-redundant-off = Повторные повороты: в очередь
+gravity-down = Гравитация: вниз
+gravity-up = Гравитация: вверх
+gravity-left = Гравитация: влево
+gravity-right = Гравитация: вправо
+gravity-center = Гравитация: к центру
+difficulty-easy = Сложность: лёгкая
+difficulty-normal = Сложность: обычная
+difficulty-hard = Сложность: сложная
+difficulty-expert = Сложность: эксперт
+skin = Облик: { $name }
+visual-theme = Тема: { $name }
+race-ghost = Гонка с лучшим забегом
+tail-safe-on = Хвост: безопасен
+tail-safe-off = Хвост: смертелен
+invulnerable-steps = Защита после роста: { $steps }
+bite-off-on = Укус: отсекает хвост
+bite-off-off = Укус: конец забега
+back = Назад
+made-with-bevy = Сделано на Bevy
+open-source = Открытый код
+
+## Modes
+mode-endless = Бесконечный
+mode-time-attack = На время
+mode-zen = Дзен
+no-high-scores = Рекордов пока нет
+best-length = Лучшая длина: { $score }
+best-gems = Лучший счёт: { $score }
+
+## HUD
+snake-length = Длина змеи: { $length }
+record-length = Рекорд длины: { $length }
+gems-destroyed = Уничтожено камней: { $count }
+total-destroyed = Всего уничтожено: { $count }
+largest-chain = Самая длинная цепь: { $count }
+next-growth = Рост
+time-left = Осталось: { $time }
+power-up-slow-time = Замедление
+power-up-ghost = Призрак
+power-up-detonate = Взрыв
+power-up-shrink = Уменьшение
+cascade-count = Цепочка: { $count }
+cascade-skip = Нажмите любую клавишу, чтобы пропустить
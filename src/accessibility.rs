@@ -0,0 +1,229 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::MoveInput,
+    clock::GameClock,
+    gems::GemType,
+    loading::TextureAssets,
+    player::{GridPosition, SnakeHead},
+    storage,
+    themes::ActiveTheme,
+    GamePhase,
+};
+
+/// Storage key of the [`Accessibility`] settings
+const SETTINGS_KEY: &str = "accessibility.ron";
+
+/// Game speeds the settings cycle through, in percent
+const GAME_SPEEDS: [u32; 4] = [100, 75, 50, 25];
+
+pub struct AccessibilityPlugin;
+
+/// This plugin applies the accessibility settings
+/// Gems can use a palette for protanopia, deuteranopia or tritanopia and show a symbol per type.
+/// The whole game can run slower, or wait for an input before every step of the snake.
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Accessibility::load())
+            .add_observer(add_gem_symbol)
+            .add_systems(
+                OnEnter(GamePhase::Playing),
+                wait_for_turn.run_if(turn_based),
+            )
+            .add_systems(OnExit(GamePhase::Playing), resume_time)
+            .add_systems(
+                Update,
+                step_turns
+                    .run_if(turn_based)
+                    .run_if(in_state(GamePhase::Playing)),
+            )
+            .add_systems(
+                Update,
+                (
+                    save_settings.run_if(
+                        resource_changed::<Accessibility>.and(not(resource_added::<Accessibility>)),
+                    ),
+                    restyle_gems.run_if(
+                        resource_exists::<TextureAssets>.and(
+                            resource_changed::<Accessibility>
+                                .and(not(resource_added::<Accessibility>))
+                                .or(resource_changed::<ActiveTheme>),
+                        ),
+                    ),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Accessibility {
+    pub palette: Palette,
+    /// Draw a distinct symbol on every gem type, so colour is not the only cue
+    pub symbols: bool,
+    /// Speed of all gameplay timers and animations in percent
+    pub game_speed: u32,
+    /// The snake only moves one tile per input
+    pub turn_based: bool,
+}
+
+impl Default for Accessibility {
+    fn default() -> Self {
+        Accessibility {
+            palette: Palette::default(),
+            symbols: false,
+            game_speed: 100,
+            turn_based: false,
+        }
+    }
+}
+
+impl Accessibility {
+    fn load() -> Self {
+        storage::read(SETTINGS_KEY)
+            .and_then(|settings| Ok(ron::from_str(&settings)?))
+            .unwrap_or_default()
+    }
+
+    /// The next slower game speed, starting over at full speed
+    pub fn next_game_speed(&self) -> u32 {
+        GAME_SPEEDS
+            .into_iter()
+            .find(|speed| *speed < self.game_speed)
+            .unwrap_or(GAME_SPEEDS[0])
+    }
+}
+
+/// Gem colours, the alternatives keep all gem types apart for the named colour vision deficiency
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Palette {
+    #[default]
+    Standard,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 4] = [
+        Palette::Standard,
+        Palette::Protanopia,
+        Palette::Deuteranopia,
+        Palette::Tritanopia,
+    ];
+
+    pub fn next(self) -> Self {
+        let index = Palette::ALL
+            .iter()
+            .position(|palette| *palette == self)
+            .unwrap_or_default();
+        Palette::ALL[(index + 1) % Palette::ALL.len()]
+    }
+
+    /// Translation key of the menu label
+    pub fn key(&self) -> &'static str {
+        match self {
+            Palette::Standard => "palette-standard",
+            Palette::Protanopia => "palette-protanopia",
+            Palette::Deuteranopia => "palette-deuteranopia",
+            Palette::Tritanopia => "palette-tritanopia",
+        }
+    }
+}
+
+fn save_settings(accessibility: Res<Accessibility>) {
+    let saved = ron::to_string(&*accessibility)
+        .map_err(BevyError::from)
+        .and_then(|settings| storage::write(SETTINGS_KEY, &settings));
+    if let Err(error) = saved {
+        warn!("Failed to save accessibility settings: {error}");
+    }
+}
+
+#[derive(Component)]
+struct GemSymbol;
+
+/// Symbol of the gem type, to be added as a child of the gem
+pub fn gem_symbol(textures: &TextureAssets, gem: &GemType) -> impl Bundle {
+    (
+        Sprite::from_atlas_image(
+            textures.symbols.clone(),
+            TextureAtlas {
+                layout: textures.symbols_layout.clone(),
+                index: gem.index(),
+            },
+        ),
+        Transform::from_xyz(0., 0., 0.2),
+        GemSymbol,
+    )
+}
+
+fn add_gem_symbol(
+    trigger: Trigger<OnAdd, GemType>,
+    mut commands: Commands,
+    gems: Query<&GemType>,
+    accessibility: Res<Accessibility>,
+    textures: Option<Res<TextureAssets>>,
+) {
+    let (Some(textures), Ok(gem)) = (textures, gems.get(trigger.target())) else {
+        return;
+    };
+    if accessibility.symbols {
+        commands
+            .entity(trigger.target())
+            .with_child(gem_symbol(&textures, gem));
+    }
+}
+
+/// Gems on the board keep their old look otherwise, until they are destroyed
+///
+/// Also runs when the theme changes, since themes can replace the standard gems.
+fn restyle_gems(
+    mut commands: Commands,
+    accessibility: Res<Accessibility>,
+    theme: Res<ActiveTheme>,
+    textures: Res<TextureAssets>,
+    mut gems: Query<(Entity, &GemType, &mut Sprite)>,
+    symbols: Query<Entity, With<GemSymbol>>,
+) {
+    for symbol in &symbols {
+        commands.entity(symbol).despawn();
+    }
+    for (entity, gem, mut sprite) in &mut gems {
+        sprite.image = textures.gem(gem, accessibility.palette, &theme);
+        if accessibility.symbols {
+            commands
+                .entity(entity)
+                .with_child(gem_symbol(&textures, gem));
+        }
+    }
+}
+
+fn turn_based(accessibility: Res<Accessibility>) -> bool {
+    accessibility.turn_based
+}
+
+/// Between turns the game stands still, including the growth and time limit timers
+fn wait_for_turn(mut clock: ResMut<GameClock>) {
+    clock.waiting_for_turn = true;
+}
+
+/// Cascades, menus and everything outside of moving the snake run without waiting for input
+fn resume_time(mut clock: ResMut<GameClock>) {
+    clock.waiting_for_turn = false;
+}
+
+/// Every input lets the snake move a single tile further
+fn step_turns(
+    mut inputs: EventReader<MoveInput>,
+    head: Query<(), (With<SnakeHead>, Changed<GridPosition>)>,
+    mut clock: ResMut<GameClock>,
+) {
+    if !head.is_empty() {
+        clock.waiting_for_turn = true;
+    }
+    if inputs.read().count() > 0 {
+        clock.waiting_for_turn = false;
+    }
+}
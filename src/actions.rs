@@ -1,13 +1,23 @@
-use std::f32::consts::PI;
+use std::{collections::VecDeque, f32::consts::PI};
 
 use bevy::prelude::*;
 use bevy_enhanced_input::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     grid::{GRID_HEIGHT, GRID_WIDTH},
+    locale::Localized,
+    navigation::Activate,
     player::{GridPosition, SnakeHead},
+    storage,
 };
 
+/// Storage key of the [`TurnBuffer`]
+const TURN_BUFFER_KEY: &str = "turn_buffer.ron";
+
+/// Choices of [`TurnBuffer::size`] in the settings
+const TURN_BUFFER_SIZES: [usize; 4] = [0, 1, 3, 5];
+
 pub struct ActionsPlugin;
 
 // This plugin listens for keyboard input and converts the input into Actions.
@@ -16,6 +26,18 @@ impl Plugin for ActionsPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(EnhancedInputPlugin)
             .add_input_context::<Player>()
+            .add_event::<MoveInput>()
+            .insert_resource(TurnBuffer::load())
+            .add_observer(activate_turn_buffer_button)
+            .add_systems(
+                Update,
+                (
+                    save_turn_buffer.run_if(
+                        resource_changed::<TurnBuffer>.and(not(resource_added::<TurnBuffer>)),
+                    ),
+                    update_turn_buffer_text.run_if(resource_changed::<TurnBuffer>),
+                ),
+            )
             .add_observer(player_binding)
             .add_observer(next_move_straight)
             .add_observer(next_move_right)
@@ -27,24 +49,50 @@ fn player_binding(trigger: Trigger<Binding<Player>>, mut players: Query<&mut Act
     let mut actions = players.get_mut(trigger.target()).unwrap();
     actions
         .bind::<MoveStraight>()
-        .to((KeyCode::KeyW, KeyCode::ArrowUp, GamepadButton::DPadUp))
+        .to((
+            KeyCode::KeyW,
+            KeyCode::ArrowUp,
+            GamepadButton::DPadUp,
+            GamepadAxis::LeftStickY.with_modifiers(stick_half(false)),
+        ))
         .with_modifiers(DeadZone::default())
         .with_conditions(Pulse::new(0.2));
     actions
         .bind::<MoveRight>()
-        .to((KeyCode::KeyD, KeyCode::ArrowRight, GamepadButton::DPadRight))
+        .to((
+            KeyCode::KeyD,
+            KeyCode::ArrowRight,
+            GamepadButton::DPadRight,
+            GamepadAxis::LeftStickX.with_modifiers(stick_half(false)),
+        ))
         .with_modifiers(DeadZone::default())
         .with_conditions(Pulse::new(0.2));
     actions
         .bind::<MoveLeft>()
-        .to((KeyCode::KeyA, KeyCode::ArrowLeft, GamepadButton::DPadLeft))
+        .to((
+            KeyCode::KeyA,
+            KeyCode::ArrowLeft,
+            GamepadButton::DPadLeft,
+            GamepadAxis::LeftStickX.with_modifiers(stick_half(true)),
+        ))
         .with_modifiers(DeadZone::default())
         .with_conditions(Pulse::new(0.2));
 }
 
+/// Only one direction of a stick axis, without the noise around its center
+///
+/// Actions are booleans, so every value that is not filtered out here counts as pressed.
+fn stick_half(negative: bool) -> (Negate, Clamp, DeadZone) {
+    (Negate::splat(negative), Clamp::pos(), DeadZone::default())
+}
+
 #[derive(InputContext)]
 pub struct Player;
 
+/// Sent whenever the player steers the snake, in any direction
+#[derive(Event)]
+pub struct MoveInput;
+
 #[derive(Debug, InputAction)]
 #[input_action(output = bool)]
 struct MoveStraight;
@@ -57,7 +105,7 @@ struct MoveLeft;
 #[input_action(output = bool)]
 struct MoveRight;
 
-#[derive(Component, Debug, Clone, Copy)]
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Orientation {
     Up,
     Right,
@@ -93,6 +141,25 @@ impl Orientation {
         }
     }
 
+    pub fn z_angle(&self) -> f32 {
+        match self {
+            Orientation::Up => 0.,
+            Orientation::Right => -PI / 2.,
+            Orientation::Down => PI,
+            Orientation::Left => PI / 2.,
+        }
+    }
+
+    /// Whether moving on from `position` leaves the board on one side and comes back in on the other
+    pub fn crosses_border(&self, position: &GridPosition) -> bool {
+        match self {
+            Orientation::Up => position.y == GRID_HEIGHT - 1,
+            Orientation::Right => position.x == GRID_WIDTH - 1,
+            Orientation::Down => position.y == 0,
+            Orientation::Left => position.x == 0,
+        }
+    }
+
     pub fn next_position(&self, position: &GridPosition) -> GridPosition {
         let mut next = position.clone();
         match self {
@@ -145,6 +212,119 @@ impl Orientation {
 #[derive(Component, Debug, Clone)]
 pub struct NextMove(pub MoveDirection);
 
+/// How many turns the head remembers beyond its `NextMove`
+#[derive(Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TurnBuffer {
+    /// Zero keeps only the latest input, like before there was a buffer
+    pub size: usize,
+    /// Ignore an input that repeats the turn queued last, e.g. from holding a key
+    pub drop_redundant: bool,
+}
+
+impl Default for TurnBuffer {
+    fn default() -> Self {
+        TurnBuffer {
+            size: 3,
+            drop_redundant: true,
+        }
+    }
+}
+
+impl TurnBuffer {
+    fn load() -> Self {
+        storage::read(TURN_BUFFER_KEY)
+            .and_then(|buffer| Ok(ron::from_str(&buffer)?))
+            .unwrap_or_default()
+    }
+}
+
+fn save_turn_buffer(buffer: Res<TurnBuffer>) {
+    let saved = ron::to_string(&*buffer)
+        .map_err(BevyError::from)
+        .and_then(|buffer| storage::write(TURN_BUFFER_KEY, &buffer));
+    if let Err(error) = saved {
+        warn!("Failed to save turn buffer: {error}");
+    }
+}
+
+/// Menu button changing the [`TurnBuffer`]
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum TurnBufferButton {
+    Size,
+    DropRedundant,
+}
+
+/// Label of a [`TurnBufferButton`]
+#[derive(Component)]
+pub struct TurnBufferText(pub TurnBufferButton);
+
+pub fn turn_buffer_label(button: TurnBufferButton, buffer: &TurnBuffer) -> Localized {
+    match button {
+        TurnBufferButton::Size => Localized::new("turn-buffer").with("turns", buffer.size),
+        TurnBufferButton::DropRedundant => Localized::new(if buffer.drop_redundant {
+            "drop-redundant-on"
+        } else {
+            "drop-redundant-off"
+        }),
+    }
+}
+
+fn update_turn_buffer_text(
+    buffer: Res<TurnBuffer>,
+    mut texts: Query<(&mut Localized, &TurnBufferText)>,
+) {
+    for (mut text, TurnBufferText(button)) in &mut texts {
+        text.set_if_neq(turn_buffer_label(*button, &buffer));
+    }
+}
+
+fn activate_turn_buffer_button(
+    trigger: Trigger<Activate>,
+    buttons: Query<&TurnBufferButton>,
+    mut buffer: ResMut<TurnBuffer>,
+) {
+    let Ok(button) = buttons.get(trigger.target()) else {
+        return;
+    };
+    match button {
+        TurnBufferButton::Size => {
+            let index = TURN_BUFFER_SIZES
+                .iter()
+                .position(|size| *size == buffer.size)
+                .map_or(0, |index| (index + 1) % TURN_BUFFER_SIZES.len());
+            buffer.size = TURN_BUFFER_SIZES[index];
+        }
+        TurnBufferButton::DropRedundant => buffer.drop_redundant = !buffer.drop_redundant,
+    }
+}
+
+/// Turns of the head for the tile steps after the current one, oldest first
+#[derive(Component, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TurnQueue(VecDeque<MoveDirection>);
+
+impl TurnQueue {
+    /// Takes the move for the next tile step
+    pub fn pop(&mut self) -> MoveDirection {
+        self.0.pop_front().unwrap_or(MoveDirection::Straight)
+    }
+
+    fn push(&mut self, next_move: &mut NextMove, direction: MoveDirection, buffer: &TurnBuffer) {
+        // Nothing was decided for the current step yet
+        if buffer.size == 0 || (self.0.is_empty() && next_move.0 == MoveDirection::Straight) {
+            next_move.0 = direction;
+            return;
+        }
+        let last = self.0.back().copied().unwrap_or(next_move.0);
+        if buffer.drop_redundant && last == direction {
+            return;
+        }
+        if self.0.len() < buffer.size {
+            self.0.push_back(direction);
+        }
+    }
+}
+
 impl NextMove {
     pub fn z_angle(&self) -> f32 {
         match self.0 {
@@ -155,7 +335,7 @@ impl NextMove {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MoveDirection {
     Left,
     Straight,
@@ -164,34 +344,43 @@ pub enum MoveDirection {
 
 fn next_move_straight(
     trigger: Trigger<Fired<MoveStraight>>,
-    players: Query<&mut NextMove, With<SnakeHead>>,
+    players: Query<(&mut NextMove, &mut TurnQueue), With<SnakeHead>>,
+    buffer: Res<TurnBuffer>,
+    mut writer: EventWriter<MoveInput>,
 ) {
     if trigger.value {
-        for mut next_move in players {
-            next_move.0 = MoveDirection::Straight;
+        writer.write(MoveInput);
+        for (mut next_move, mut queue) in players {
+            queue.push(&mut next_move, MoveDirection::Straight, &buffer);
         }
     }
 }
 
 fn next_move_left(
     trigger: Trigger<Fired<MoveLeft>>,
-    players: Query<&mut NextMove, With<SnakeHead>>,
+    players: Query<(&mut NextMove, &mut TurnQueue), With<SnakeHead>>,
+    buffer: Res<TurnBuffer>,
+    mut writer: EventWriter<MoveInput>,
 ) {
     if trigger.value {
-        for mut next_move in players {
-            next_move.0 = MoveDirection::Left;
+        writer.write(MoveInput);
+        for (mut next_move, mut queue) in players {
+            queue.push(&mut next_move, MoveDirection::Left, &buffer);
         }
     }
 }
 
 fn next_move_right(
     trigger: Trigger<Fired<MoveRight>>,
-    players: Query<&mut NextMove, With<SnakeHead>>,
+    players: Query<(&mut NextMove, &mut TurnQueue), With<SnakeHead>>,
+    buffer: Res<TurnBuffer>,
+    mut writer: EventWriter<MoveInput>,
 ) {
     if trigger.value {
+        writer.write(MoveInput);
         info!("turning right");
-        for mut next_move in players {
-            next_move.0 = MoveDirection::Right;
+        for (mut next_move, mut queue) in players {
+            queue.push(&mut next_move, MoveDirection::Right, &buffer);
         }
     }
 }
@@ -1,17 +1,15 @@
 use bevy::prelude::*;
 
-use crate::{loading::AudioAssets, GameState};
+use crate::loading::AudioAssets;
 
 pub struct InternalAudioPlugin;
 
 impl Plugin for InternalAudioPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<SoundEffect>()
-            .add_systems(OnExit(GameState::Loading), start_audio)
-            .add_systems(
-                Update,
-                play_sound_effect.run_if(resource_exists::<AudioAssets>),
-            );
+        app.add_event::<SoundEffect>().add_systems(
+            Update,
+            play_sound_effect.run_if(resource_exists::<AudioAssets>),
+        );
     }
 }
 
@@ -32,13 +30,6 @@ fn play_sound_effect(
     }
 }
 
-fn start_audio(audio: Res<AudioAssets>, mut commands: Commands) {
-    commands.spawn((
-        AudioPlayer::new(audio.background.clone()),
-        PlaybackSettings::LOOP,
-    ));
-}
-
 #[derive(Event)]
 pub enum SoundEffect {
     Lost,
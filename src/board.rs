@@ -3,38 +3,53 @@ use std::ops::Deref;
 use crate::{
     actions::Orientation,
     audio::SoundEffect,
-    gems::{Falling, GemType},
-    grid::{GRID_HEIGHT, GRID_WIDTH, TILE_SIZE},
-    loading::TextureAssets,
+    gems::{Falling, GemImages, GemType, LockOverlay},
+    generator::{generate_without_match, ActiveGenerator, Filled},
+    gravity::Gravity,
+    grid::{position_to_transform, Walls, GRID_HEIGHT, GRID_WIDTH, TILE_SIZE},
+    level::StartingLevel,
+    mode::GameMode,
     player::{ActivePositions, GridPosition, SnakeHead, SnakePart, SnakeTail},
+    power_ups::{detonated, Detonation},
+    save::ResumedRun,
     ui::{BiggestChainReaction, Explosions, ExplosionsTotal},
     AppSystems, GamePhase, GameState,
 };
 use bevy::{platform::collections::HashSet, prelude::*};
 use bevy_rand::{global::GlobalEntropy, prelude::ChaCha8Rng};
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
 
 pub struct BoardPlugin;
 
 impl Plugin for BoardPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Playing), fill_board)
-            .add_systems(
-                Update,
-                (
-                    explode
-                        .in_set(AppSystems::Match)
-                        .run_if(in_state(GamePhase::Playing))
-                        .run_if(resource_changed::<ActivePositions>),
-                    tail_manipulation
-                        .in_set(AppSystems::Manipulate)
-                        .run_if(in_state(GamePhase::Playing))
-                        .run_if(|exploding: Query<&Exploding>| exploding.is_empty()),
-                    animate_exploding_gems
-                        .in_set(AppSystems::Match)
-                        .run_if(in_state(GamePhase::Exploding)),
-                ),
-            )
-            .add_systems(OnEnter(GamePhase::Exploding), reset_exploding_timer);
+        app.add_systems(
+            OnEnter(GameState::Playing),
+            fill_board.run_if(not(resource_exists::<ResumedRun>)),
+        )
+        .add_systems(
+            Update,
+            (
+                explode
+                    .in_set(AppSystems::Match)
+                    .run_if(in_state(GamePhase::Playing))
+                    .run_if(resource_changed::<ActivePositions>.or(resource_exists::<Detonation>)),
+                tail_manipulation
+                    .in_set(AppSystems::Manipulate)
+                    .run_if(in_state(GamePhase::Playing))
+                    .run_if(|exploding: Query<&Exploding>| exploding.is_empty()),
+                animate_exploding_gems
+                    .in_set(AppSystems::Match)
+                    .run_if(in_state(GamePhase::Exploding)),
+            ),
+        )
+        .add_systems(OnEnter(GamePhase::Exploding), reset_exploding_timer)
+        // The board settled after explosions or switches
+        .add_systems(
+            OnEnter(GamePhase::Playing),
+            reshuffle.run_if(resource_exists::<Board>),
+        );
     }
 }
 
@@ -54,94 +69,111 @@ fn tail_manipulation(
 
     *last_checked = new_position.clone();
     info!("Checking for switch");
-    let position = orientation.previous_position(new_position);
+    let position = board.previous_position(orientation, new_position);
 
-    let matches = board.find_matches(
-        1,
-        &vec![position.clone()],
-        &mut [[false; GRID_HEIGHT]; GRID_WIDTH],
-        &mut [[0; GRID_HEIGHT]; GRID_WIDTH],
-    );
-    if !matches.is_empty() {
+    let Some(target) = board.find_tail_swap(&position, new_position) else {
         return Ok(());
+    };
+    info!(
+        "Switching {}/{} with {}/{} due to match",
+        position.x, position.y, target.x, target.y
+    );
+    board.swap(&position, &target);
+    commands
+        .entity(board.gems[target.x][target.y].entity.unwrap())
+        .insert((target.clone(), Falling));
+    commands
+        .entity(board.gems[position.x][position.y].entity.unwrap())
+        .insert((position, Falling));
+
+    Ok(())
+}
+
+const MAX_SHUFFLES: usize = 100;
+
+/// Shuffle the gems on the board once the tail can no longer switch any of them into a match
+fn reshuffle(mut board: ResMut<Board>, mut rng: GlobalEntropy<ChaCha8Rng>, mut commands: Commands) {
+    if board.has_tail_swap() {
+        return;
     }
 
-    let neighboors = GridPosition::surroundings(&vec![position.clone()]);
-    for target in neighboors {
-        if &target == new_position {
-            continue;
+    let slots = board.gem_slots();
+    let mut shuffled = board.clone();
+    let mut found = false;
+    for _ in 0..MAX_SHUFFLES {
+        let mut gems = slots
+            .iter()
+            .map(|slot| {
+                (
+                    board.gems[slot.x][slot.y].clone(),
+                    board.tiles[slot.x][slot.y].clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        gems.shuffle(&mut **rng);
+        for (slot, (gem, tile)) in slots.iter().zip(gems) {
+            shuffled.gems[slot.x][slot.y] = gem;
+            shuffled.tiles[slot.x][slot.y] = tile;
         }
-        let matches = board.find_matches(
-            1,
-            &vec![target.clone()],
-            &mut [[false; GRID_HEIGHT]; GRID_WIDTH],
-            &mut [[0; GRID_HEIGHT]; GRID_WIDTH],
-        );
-        if !matches.is_empty() {
-            continue;
+        if !shuffled.has_match(&slots) && shuffled.has_tail_swap() {
+            found = true;
+            break;
         }
+    }
+    if !found {
+        warn!("No moves left, but also no shuffle that leads to new ones");
+        return;
+    }
 
-        let mut new_board = board.clone();
-        let center = new_board.gems[position.x][position.y].clone();
-        new_board.gems[position.x][position.y] = new_board.gems[target.x][target.y].clone();
-        new_board.gems[target.x][target.y] = center;
-        let matches = new_board.find_matches(
-            1,
-            &vec![position.clone(), target.clone()],
-            &mut [[false; GRID_HEIGHT]; GRID_WIDTH],
-            &mut [[0; GRID_HEIGHT]; GRID_WIDTH],
-        );
-        if !matches.is_empty() {
-            info!(
-                "Switching {}/{} with {}/{} due to match",
-                position.x, position.y, target.x, target.y
-            );
-            commands
-                .entity(new_board.gems[target.x][target.y].entity.unwrap())
-                .insert((
-                    GridPosition {
-                        x: target.x,
-                        y: target.y,
-                    },
-                    Falling,
-                ));
-            commands
-                .entity(new_board.gems[position.x][position.y].entity.unwrap())
-                .insert((
-                    GridPosition {
-                        x: position.x,
-                        y: position.y,
-                    },
-                    Falling,
-                ));
-            *board = new_board;
-            return Ok(());
+    info!("No moves left, reshuffling the board");
+    for slot in slots {
+        let Some(entity) = shuffled.gems[slot.x][slot.y].entity else {
+            continue;
+        };
+        if board.gems[slot.x][slot.y].entity != Some(entity) {
+            commands.entity(entity).insert((slot, Falling));
         }
     }
-
-    Ok(())
+    *board = shuffled;
 }
 
 #[derive(Component)]
-struct Exploding(pub u8);
+pub struct Exploding(pub u8);
 
 #[allow(clippy::too_many_arguments)]
 fn explode(
     head: Query<&GridPosition, With<SnakeHead>>,
     mut board: ResMut<Board>,
     mut commands: Commands,
-    asset: Res<TextureAssets>,
+    gem_images: GemImages,
+    children: Query<&Children>,
+    lock_overlays: Query<(), With<LockOverlay>>,
     mut rng: GlobalEntropy<ChaCha8Rng>,
     mut next_phase: ResMut<NextState<GamePhase>>,
     mut explosions: ResMut<Explosions>,
     mut explosions_total: ResMut<ExplosionsTotal>,
     mut biggest_chain_reaction: ResMut<BiggestChainReaction>,
+    gravity: Res<Gravity>,
+    mut generator: ResMut<ActiveGenerator>,
+    detonation: Option<Res<Detonation>>,
+    snake: Res<ActivePositions>,
 ) -> Result {
     let mut checked = [[false; GRID_HEIGHT]; GRID_WIDTH];
     let mut exploding = [[0; GRID_HEIGHT]; GRID_WIDTH];
     let mut active = vec![head.single()?.clone()];
     let mut iteration = 0u8;
     let mut found = false;
+    if let Some(detonation) = detonation {
+        commands.remove_resource::<Detonation>();
+        let snake = snake.0.iter().collect::<HashSet<_>>();
+        let blast = detonated(&detonation.0, &board, &snake);
+        for position in &blast {
+            exploding[position.x][position.y] = 1;
+            found = true;
+        }
+        // Matches around the edge of the blast chain on like the ones around the head
+        active.extend(GridPosition::surroundings(&blast));
+    }
     loop {
         iteration += 1;
         let new_possitions = board.find_matches(iteration, &active, &mut checked, &mut exploding);
@@ -160,58 +192,79 @@ fn explode(
     next_phase.set(GamePhase::Exploding);
 
     let mut count = 0;
-    #[allow(clippy::needless_range_loop)]
-    for column in 0..GRID_WIDTH {
+    let mut refills = vec![];
+    for line in gravity.lines() {
+        // Gems fall past stones, holes and portals into the next free slot towards the floor
+        let slots = line
+            .slots
+            .iter()
+            .filter(|slot| board.tiles[slot.x][slot.y].holds_gem())
+            .cloned()
+            .collect::<Vec<_>>();
         let mut spawn_count = 0;
-        for y in 0..GRID_HEIGHT {
-            let Some(entity) = board.gems[column][y].entity else {
+        for (index, slot) in slots.iter().enumerate() {
+            let Some(entity) = board.gems[slot.x][slot.y].entity else {
                 error!("Missing gem entity");
                 continue;
             };
-            if exploding[column][y] > 0 {
+            if exploding[slot.x][slot.y] > 0 && board.tiles[slot.x][slot.y] == Tile::LockedGem {
+                board.tiles[slot.x][slot.y] = Tile::Gem;
+                for child in children.iter_descendants(entity) {
+                    if lock_overlays.contains(child) {
+                        commands.entity(child).despawn();
+                    }
+                }
+            } else if exploding[slot.x][slot.y] > 0 {
                 spawn_count += 1;
                 commands
                     .entity(entity)
-                    .insert(Exploding(exploding[column][y]));
-            } else if spawn_count > 0 {
-                commands.entity(entity).insert((
-                    Falling,
-                    GridPosition {
-                        x: column,
-                        y: y - spawn_count,
-                    },
-                ));
-                board.gems[column][y - spawn_count] = board.gems[column][y].clone();
+                    .insert(Exploding(exploding[slot.x][slot.y]));
+                continue;
+            }
+            if spawn_count > 0 {
+                let target = slots[index - spawn_count].clone();
+                commands.entity(entity).insert((Falling, target.clone()));
+                board.gems[target.x][target.y] = board.gems[slot.x][slot.y].clone();
+                board.tiles[target.x][target.y] = board.tiles[slot.x][slot.y].clone();
+                board.tiles[slot.x][slot.y] = Tile::Gem;
             }
         }
         count += spawn_count;
+        let Some(edge) = line.slots.last() else {
+            continue;
+        };
+        let edge = position_to_transform(edge) + line.inflow * TILE_SIZE;
         for spawn in 1..=spawn_count {
-            let gem_type = GemType::random(&mut rng);
-            let position = GridPosition {
-                x: column,
-                y: GRID_HEIGHT - spawn,
-            };
-            let id = commands
-                .spawn((
-                    Transform::from_xyz(
-                        (-(GRID_WIDTH as f32) / 2. + position.x as f32 + 0.5) * TILE_SIZE,
-                        TILE_SIZE * (GRID_HEIGHT as f32) / 2.
-                            + (spawn_count - spawn + 1) as f32 * TILE_SIZE * 1.5,
-                        0.,
-                    ),
-                    Sprite::from_image(asset.gem(&gem_type)),
-                    gem_type.clone(),
-                    position,
-                    Falling,
-                ))
-                .id();
-            board.gems[column][GRID_HEIGHT - spawn] = Gem {
-                gem_type,
-                entity: Some(id),
-            };
+            refills.push((
+                slots[slots.len() - spawn].clone(),
+                edge + line.inflow * (spawn_count - spawn + 1) as f32 * TILE_SIZE * 1.5,
+            ));
         }
     }
 
+    // Refill only once everything fell, so generators see the final neighbours of each slot
+    let mut filled = [[true; GRID_HEIGHT]; GRID_WIDTH];
+    for (position, _) in &refills {
+        filled[position.x][position.y] = false;
+    }
+    for (position, translation) in refills {
+        let gem_type = generator.0.generate(&board, &filled, &position, &mut rng);
+        let id = commands
+            .spawn((
+                Transform::from_translation(translation.extend(0.)),
+                Sprite::from_image(gem_images.get(&gem_type)),
+                gem_type.clone(),
+                position.clone(),
+                Falling,
+            ))
+            .id();
+        filled[position.x][position.y] = true;
+        board.gems[position.x][position.y] = Gem {
+            gem_type,
+            entity: Some(id),
+        };
+    }
+
     explosions.0 += count;
     explosions_total.0 += count;
     if count > biggest_chain_reaction.0 {
@@ -231,6 +284,7 @@ fn reset_exploding_timer(mut commands: Commands) {
     )));
 }
 
+#[allow(clippy::too_many_arguments)]
 fn animate_exploding_gems(
     exploding: Query<(Entity, &GridPosition, &mut Exploding), Without<SnakePart>>,
     snake_body: Query<&GridPosition, (With<SnakePart>, Without<SnakeHead>)>,
@@ -239,6 +293,7 @@ fn animate_exploding_gems(
     time: Res<Time>,
     mut next_phase: ResMut<NextState<GamePhase>>,
     mut writer: EventWriter<SoundEffect>,
+    mode: Res<GameMode>,
 ) {
     if exploding.is_empty() {
         next_phase.set(GamePhase::Waiting);
@@ -248,7 +303,7 @@ fn animate_exploding_gems(
         writer.write(SoundEffect::GemMatch);
         for (entity, position, mut exploding) in exploding {
             if exploding.0 == 1 {
-                if snake_body.iter().any(|body| body == position) {
+                if *mode != GameMode::Zen && snake_body.iter().any(|body| body == position) {
                     info!("Snake got hit by match at {}/{}", position.x, position.y);
                     next_phase.set(GamePhase::Lost);
                     writer.write(SoundEffect::Lost);
@@ -264,15 +319,169 @@ fn animate_exploding_gems(
 #[derive(Resource, Default, Clone)]
 pub struct Board {
     pub gems: [[Gem; GRID_HEIGHT]; GRID_WIDTH],
+    pub tiles: [[Tile; GRID_HEIGHT]; GRID_WIDTH],
+}
+
+/// Content of a grid cell besides the gem in it
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Tile {
+    #[default]
+    Gem,
+    /// Holds a gem that only explodes on its second match
+    LockedGem,
+    /// Blocks the snake
+    Stone,
+    /// Stays empty
+    Hole,
+    /// Moving into a portal continues behind the linked portal
+    Portal(GridPosition),
+}
+
+impl Tile {
+    pub fn holds_gem(&self) -> bool {
+        matches!(self, Tile::Gem | Tile::LockedGem)
+    }
 }
 
 impl Board {
-    pub fn randomize_gems(&mut self, rng: &mut GlobalEntropy<ChaCha8Rng>) {
-        for x in 0..GRID_WIDTH {
-            for y in 0..GRID_HEIGHT {
-                self.gems[x][y].gem_type = GemType::random(rng);
+    fn gem_at(&self, x: usize, y: usize) -> Option<&GemType> {
+        self.tiles[x][y]
+            .holds_gem()
+            .then_some(&self.gems[x][y].gem_type)
+    }
+
+    /// Next position of something moving from `position` in the given orientation, passing through portals
+    ///
+    /// Returns `None` if the move runs into a wall.
+    pub fn next_position(
+        &self,
+        orientation: &Orientation,
+        position: &GridPosition,
+        walls: &Walls,
+    ) -> Option<GridPosition> {
+        let mut current = position;
+        let mut next;
+        let mut steps = 0;
+        loop {
+            if walls.0 && orientation.crosses_border(current) {
+                return None;
+            }
+            next = orientation.next_position(current);
+            steps += 1;
+            let Tile::Portal(exit) = &self.tiles[next.x][next.y] else {
+                break;
+            };
+            if steps > GRID_WIDTH * GRID_HEIGHT {
+                break;
+            }
+            current = exit;
+        }
+
+        Some(next)
+    }
+
+    /// Inverse of [`Board::next_position`]
+    pub fn previous_position(
+        &self,
+        orientation: &Orientation,
+        position: &GridPosition,
+    ) -> GridPosition {
+        let mut previous = orientation.previous_position(position);
+        for _ in 0..GRID_WIDTH * GRID_HEIGHT {
+            let Tile::Portal(entry) = &self.tiles[previous.x][previous.y] else {
+                break;
+            };
+            previous = orientation.previous_position(entry);
+        }
+
+        previous
+    }
+
+    /// Whether `gem_type` at `position` would line up with at least two filled gems of the same kind
+    pub fn completes_match(
+        &self,
+        filled: &Filled,
+        position: &GridPosition,
+        gem_type: &GemType,
+    ) -> bool {
+        let same = |x: i32, y: i32| {
+            in_bounds(x as usize, y as usize)
+                && filled[x as usize][y as usize]
+                && self.gem_at(x as usize, y as usize) == Some(gem_type)
+        };
+        let run = |dx: i32, dy: i32| {
+            (1..)
+                .take_while(|&step| {
+                    same(position.x as i32 + dx * step, position.y as i32 + dy * step)
+                })
+                .count()
+        };
+
+        run(1, 0) + run(-1, 0) >= 2 || run(0, 1) + run(0, -1) >= 2
+    }
+
+    pub fn swap(&mut self, a: &GridPosition, b: &GridPosition) {
+        let gem = self.gems[a.x][a.y].clone();
+        self.gems[a.x][a.y] = self.gems[b.x][b.y].clone();
+        self.gems[b.x][b.y] = gem;
+    }
+
+    fn gem_slots(&self) -> Vec<GridPosition> {
+        (0..GRID_WIDTH)
+            .flat_map(|x| (0..GRID_HEIGHT).map(move |y| GridPosition { x, y }))
+            .filter(|slot| self.tiles[slot.x][slot.y].holds_gem())
+            .collect()
+    }
+
+    /// Whether the tail could switch any gem on the board into a match
+    pub fn has_tail_swap(&self) -> bool {
+        self.gem_slots()
+            .iter()
+            .any(|slot| self.find_tail_swap(slot, slot).is_some())
+    }
+
+    fn has_match(&self, slots: &Vec<GridPosition>) -> bool {
+        !self
+            .find_matches(
+                1,
+                slots,
+                &mut [[false; GRID_HEIGHT]; GRID_WIDTH],
+                &mut [[0; GRID_HEIGHT]; GRID_WIDTH],
+            )
+            .is_empty()
+    }
+
+    /// Find the neighbour that the gem at `position` would be switched with when the tail leaves it
+    ///
+    /// `next` is the cell the tail moves into and is never considered as a target.
+    pub fn find_tail_swap(
+        &self,
+        position: &GridPosition,
+        next: &GridPosition,
+    ) -> Option<GridPosition> {
+        if !self.tiles[position.x][position.y].holds_gem()
+            || self.has_match(&vec![position.clone()])
+        {
+            return None;
+        }
+
+        let neighboors = GridPosition::surroundings(&vec![position.clone()]);
+        for target in neighboors {
+            if &target == next
+                || !self.tiles[target.x][target.y].holds_gem()
+                || self.has_match(&vec![target.clone()])
+            {
+                continue;
+            }
+
+            let mut new_board = self.clone();
+            new_board.swap(position, &target);
+            if new_board.has_match(&vec![position.clone(), target.clone()]) {
+                return Some(target);
             }
         }
+
+        None
     }
 
     fn find_matches(
@@ -302,7 +511,9 @@ impl Board {
             return false;
         }
         checked[slot.x][slot.y] = true;
-        let gem_type = self.gems[slot.x][slot.y].gem_type.clone();
+        let Some(gem_type) = self.gem_at(slot.x, slot.y).cloned() else {
+            return false;
+        };
 
         let mut x_diff = 1;
         let mut y_diff = 1;
@@ -312,7 +523,7 @@ impl Board {
         let mut matched_y_slot = false;
         let mut matched_y_plus = false;
         while in_bounds(slot.x + x_diff, slot.y)
-            && self.gems[slot.x + x_diff][slot.y].gem_type == gem_type
+            && self.gem_at(slot.x + x_diff, slot.y) == Some(&gem_type)
         {
             match x_diff {
                 1 => matched_x_plus = true,
@@ -329,7 +540,7 @@ impl Board {
         x_diff = 1;
         while slot.x >= x_diff
             && in_bounds(slot.x - x_diff, slot.y)
-            && self.gems[slot.x - x_diff][slot.y].gem_type == gem_type
+            && self.gem_at(slot.x - x_diff, slot.y) == Some(&gem_type)
         {
             match x_diff {
                 1 => {
@@ -354,7 +565,7 @@ impl Board {
         }
 
         while in_bounds(slot.x, slot.y + y_diff)
-            && self.gems[slot.x][slot.y + y_diff].gem_type == gem_type
+            && self.gem_at(slot.x, slot.y + y_diff) == Some(&gem_type)
         {
             match y_diff {
                 1 => matched_y_plus = true,
@@ -371,7 +582,7 @@ impl Board {
         y_diff = 1;
         while slot.y >= y_diff
             && in_bounds(slot.x, slot.y - y_diff)
-            && self.gems[slot.x][slot.y - y_diff].gem_type == gem_type
+            && self.gem_at(slot.x, slot.y - y_diff) == Some(&gem_type)
         {
             match y_diff {
                 1 => {
@@ -438,31 +649,32 @@ impl Default for Gem {
 pub fn fill_board(
     mut commands: Commands,
     mut rng: GlobalEntropy<ChaCha8Rng>,
-    snake_head: Query<&GridPosition, With<SnakeHead>>,
+    mut generator: ResMut<ActiveGenerator>,
+    level: Option<Res<StartingLevel>>,
 ) -> Result {
-    let head = snake_head.single()?;
-    let mut board = Board::default();
-    board.randomize_gems(&mut rng);
-    let surroundings = GridPosition::surroundings(&vec![head.clone()])
-        .into_iter()
-        .collect::<Vec<_>>();
-
-    let mut rounds = 0;
-    loop {
-        let matches = board.find_matches(
-            1,
-            &surroundings,
-            &mut [[false; GRID_HEIGHT]; GRID_WIDTH],
-            &mut [[0; GRID_HEIGHT]; GRID_WIDTH],
-        );
-        if matches.is_empty() {
-            break;
+    if let Some(level) = level {
+        let mut board = Board::default();
+        for x in 0..GRID_WIDTH {
+            for y in 0..GRID_HEIGHT {
+                board.gems[x][y].gem_type = level.0.gems[x][y].clone();
+                board.tiles[x][y] = level.0.tiles[x][y].clone();
+            }
         }
-        rounds += 1;
-        board.randomize_gems(&mut rng);
+        commands.insert_resource(board);
+        return Ok(());
     }
 
-    info!("Took {rounds} rounds to find valid board");
+    // The starting board never contains a match, no matter the generator
+    let mut board = Board::default();
+    let mut filled = [[false; GRID_HEIGHT]; GRID_WIDTH];
+    for x in 0..GRID_WIDTH {
+        for y in 0..GRID_HEIGHT {
+            let position = GridPosition { x, y };
+            board.gems[x][y].gem_type =
+                generate_without_match(generator.0.as_mut(), &board, &filled, &position, &mut rng);
+            filled[x][y] = true;
+        }
+    }
     commands.insert_resource(board);
 
     Ok(())
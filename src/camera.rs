@@ -0,0 +1,89 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    grid::{Walls, GRID_HEIGHT, GRID_WIDTH, TILE_SIZE, WALL_THICKNESS},
+    ui::Hud,
+};
+
+/// Space in logical pixels kept free around the board
+const MARGIN: f32 = 12.0;
+
+/// Magnification of a full [`CameraZoom`]
+const MAX_ZOOM: f32 = 1.8;
+
+pub struct CameraPlugin;
+
+/// This plugin spawns the camera and zooms it, so the whole board fits into the window below the HUD
+/// It adapts to every window size and aspect ratio, including portrait screens
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_camera)
+            .add_systems(Update, fit_board);
+    }
+}
+
+/// Moves the camera closer to a point of the board, on top of fitting the board into the window
+#[derive(Resource, Default)]
+pub struct CameraZoom {
+    /// Point in the world to move to
+    pub focus: Vec2,
+    /// From 0 for the fitted board to 1 for the closest view of `focus`
+    pub amount: f32,
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn((Camera2d, Msaa::Off));
+}
+
+/// Size of the board in world units, including walls if there are any
+fn board_size(walls: &Walls) -> Vec2 {
+    let size = Vec2::new(GRID_WIDTH as f32, GRID_HEIGHT as f32) * TILE_SIZE;
+    if walls.0 {
+        size + 2. * WALL_THICKNESS
+    } else {
+        size
+    }
+}
+
+fn fit_board(
+    window: Query<&Window, With<PrimaryWindow>>,
+    hud: Query<&ComputedNode, With<Hud>>,
+    walls: Res<Walls>,
+    zoom: Option<Res<CameraZoom>>,
+    mut camera: Query<(&mut Projection, &mut Transform), With<Camera2d>>,
+) -> Result {
+    let window = window.single()?;
+    let (mut projection, mut transform) = camera.single_mut()?;
+    let Projection::Orthographic(orthographic) = projection.as_ref() else {
+        return Ok(());
+    };
+
+    // The HUD wraps onto more rows in narrow windows, so its height is measured instead of guessed
+    let hud_height = hud
+        .iter()
+        .map(|node| node.size().y * node.inverse_scale_factor())
+        .sum::<f32>();
+    let top = hud_height + MARGIN;
+    let available = Vec2::new(
+        (window.width() - 2. * MARGIN).max(1.),
+        (window.height() - top - MARGIN).max(1.),
+    );
+    let mut scale = (board_size(&walls) / available).max_element();
+    // Board center, moved down to the middle of the space below the HUD
+    let mut translation = Vec2::new(0., -TILE_SIZE / 2. + (top - MARGIN) / 2. * scale);
+    if let Some(zoom) = zoom {
+        translation = translation.lerp(zoom.focus, zoom.amount);
+        scale *= 1. - zoom.amount * (1. - 1. / MAX_ZOOM);
+    }
+
+    if orthographic.scale != scale {
+        if let Projection::Orthographic(orthographic) = projection.as_mut() {
+            orthographic.scale = scale;
+        }
+    }
+    if transform.translation.truncate() != translation {
+        transform.translation = translation.extend(transform.translation.z);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use bevy::prelude::*;
+
+use crate::{
+    board::Exploding,
+    camera::CameraZoom,
+    clock::GameClock,
+    grid::position_to_transform,
+    locale::Localized,
+    player::GridPosition,
+    theme::{label, SMALL_FONT_SIZE, SPACING, TITLE_FONT_SIZE},
+    GamePhase,
+};
+
+/// Gems a cascade needs to be shown in slow motion
+const SLOW_MOTION_GEMS: usize = 15;
+/// Speed of the game during the slow motion, on top of the game speed setting
+const SLOW_MOTION_SPEED: f32 = 0.4;
+/// Share of the zoom gained or lost per real second
+const ZOOM_SPEED: f32 = 2.5;
+/// How quickly the camera catches up with the wave front, higher is faster
+const FOCUS_SPEED: f32 = 6.;
+
+pub struct CascadePlugin;
+
+/// This plugin plays big cascades in slow motion with the camera following the wave front
+/// A counter shows the gems that exploded so far. Any input skips back to the normal view.
+impl Plugin for CascadePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GamePhase::Exploding), start_slow_motion)
+            .add_systems(OnExit(GamePhase::Exploding), stop_slow_motion)
+            .add_systems(
+                Update,
+                (
+                    (follow_wave_front, update_counter, skip_slow_motion)
+                        .chain()
+                        .run_if(resource_exists::<SlowMotion>),
+                    release_camera.run_if(
+                        resource_exists::<CameraZoom>.and(not(resource_exists::<SlowMotion>)),
+                    ),
+                ),
+            );
+    }
+}
+
+/// Present while a cascade plays in slow motion
+#[derive(Resource)]
+struct SlowMotion {
+    /// Gems of the whole cascade
+    total: usize,
+}
+
+/// Root of the counter shown during the slow motion
+#[derive(Component)]
+struct CascadeCounter;
+
+#[derive(Component)]
+struct CascadeCountText;
+
+/// Middle of the gems that explode next
+fn wave_front<'a>(
+    exploding: impl Iterator<Item = (&'a GridPosition, &'a Exploding)>,
+) -> Option<Vec2> {
+    let gems = exploding.collect::<Vec<_>>();
+    let next = gems.iter().map(|(_, exploding)| exploding.0).min()?;
+    let front = gems
+        .iter()
+        .filter(|(_, exploding)| exploding.0 == next)
+        .map(|(position, _)| position_to_transform(position))
+        .collect::<Vec<_>>();
+
+    Some(front.iter().sum::<Vec2>() / front.len() as f32)
+}
+
+fn start_slow_motion(
+    mut commands: Commands,
+    exploding: Query<(&GridPosition, &Exploding)>,
+    zoom: Option<Res<CameraZoom>>,
+    mut clock: ResMut<GameClock>,
+) {
+    let total = exploding.iter().len();
+    if total < SLOW_MOTION_GEMS {
+        return;
+    }
+    let Some(focus) = wave_front(exploding.iter()) else {
+        return;
+    };
+
+    info!("Playing a cascade of {total} gems in slow motion");
+    commands.insert_resource(SlowMotion { total });
+    // A zoom that is still easing out continues from where it is
+    commands.insert_resource(CameraZoom {
+        focus,
+        amount: zoom.map_or(0., |zoom| zoom.amount),
+    });
+    clock.slow_motion = Some(SLOW_MOTION_SPEED);
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                bottom: Val::Px(2. * SPACING),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            Pickable::IGNORE,
+            CascadeCounter,
+        ))
+        .with_children(|counter| {
+            counter.spawn((
+                label(
+                    Localized::new("cascade-count").with("count", 0),
+                    TITLE_FONT_SIZE,
+                ),
+                CascadeCountText,
+            ));
+            counter.spawn(label(Localized::new("cascade-skip"), SMALL_FONT_SIZE));
+        });
+}
+
+/// The camera eases back out on its own
+fn end_slow_motion(
+    commands: &mut Commands,
+    clock: &mut GameClock,
+    counters: &Query<Entity, With<CascadeCounter>>,
+) {
+    commands.remove_resource::<SlowMotion>();
+    clock.slow_motion = None;
+    for counter in counters {
+        commands.entity(counter).despawn();
+    }
+}
+
+fn stop_slow_motion(
+    mut commands: Commands,
+    slow_motion: Option<Res<SlowMotion>>,
+    mut clock: ResMut<GameClock>,
+    counters: Query<Entity, With<CascadeCounter>>,
+) {
+    if slow_motion.is_some() {
+        end_slow_motion(&mut commands, &mut clock, &counters);
+    }
+}
+
+/// Runs on real time, so the camera keeps its pace in slow motion
+fn follow_wave_front(
+    time: Res<Time<Real>>,
+    exploding: Query<(&GridPosition, &Exploding)>,
+    mut zoom: ResMut<CameraZoom>,
+) {
+    let delta = time.delta_secs();
+    zoom.amount = (zoom.amount + ZOOM_SPEED * delta).min(1.);
+    if let Some(front) = wave_front(exploding.iter()) {
+        let focus = zoom.focus;
+        zoom.focus = focus.lerp(front, 1. - (-FOCUS_SPEED * delta).exp());
+    }
+}
+
+fn update_counter(
+    slow_motion: Res<SlowMotion>,
+    exploding: Query<(), With<Exploding>>,
+    mut texts: Query<&mut Localized, With<CascadeCountText>>,
+) {
+    let count = slow_motion.total - exploding.iter().len().min(slow_motion.total);
+    for mut text in &mut texts {
+        text.set_if_neq(Localized::new("cascade-count").with("count", count));
+    }
+}
+
+/// Any key, mouse button, gamepad button or touch
+#[allow(clippy::too_many_arguments)]
+fn skip_slow_motion(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
+    touches: Res<Touches>,
+    mut clock: ResMut<GameClock>,
+    counters: Query<Entity, With<CascadeCounter>>,
+) {
+    let pressed = keys.get_just_pressed().next().is_some()
+        || mouse.get_just_pressed().next().is_some()
+        || gamepads
+            .iter()
+            .any(|gamepad| gamepad.get_just_pressed().next().is_some())
+        || touches.any_just_pressed();
+    if pressed {
+        info!("Skipped the slow motion");
+        end_slow_motion(&mut commands, &mut clock, &counters);
+    }
+}
+
+fn release_camera(mut commands: Commands, time: Res<Time<Real>>, mut zoom: ResMut<CameraZoom>) {
+    zoom.amount -= ZOOM_SPEED * time.delta_secs();
+    if zoom.amount <= 0. {
+        commands.remove_resource::<CameraZoom>();
+    }
+}
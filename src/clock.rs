@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+
+use crate::accessibility::Accessibility;
+
+pub struct ClockPlugin;
+
+/// This plugin is the only one to pause or scale the virtual time that gameplay runs on
+/// Everything that wants to stop or slow down the game says so in the [`GameClock`], so turn-based
+/// play, slow motion and debug stepping can not undo each other.
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameClock>().add_systems(
+            Last,
+            apply_clock.run_if(resource_changed::<GameClock>.or(resource_changed::<Accessibility>)),
+        );
+    }
+}
+
+/// Reasons for the game to stand still or run slower than the game speed setting
+#[derive(Resource, Default)]
+pub struct GameClock {
+    /// Turn-based play waits for the next input
+    pub waiting_for_turn: bool,
+    /// Gameplay is frozen for debugging, except for single steps
+    pub frozen: bool,
+    /// Speed on top of the game speed setting while a cascade plays in slow motion
+    pub slow_motion: Option<f32>,
+}
+
+/// Scaling virtual time slows down every timer and animation that runs on [`Time`]
+fn apply_clock(
+    clock: Res<GameClock>,
+    accessibility: Res<Accessibility>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    time.set_relative_speed(
+        accessibility.game_speed as f32 / 100. * clock.slow_motion.unwrap_or(1.),
+    );
+    if clock.waiting_for_turn || clock.frozen {
+        time.pause();
+    } else {
+        time.unpause();
+    }
+}
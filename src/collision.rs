@@ -0,0 +1,303 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    level::SNAKE_START_LENGTH, locale::Localized, navigation::Activate, storage, GameState,
+};
+
+/// Storage key of the [`CollisionRules`]
+const COLLISION_KEY: &str = "collision.ron";
+
+/// Choices of [`CollisionRules::invulnerable_steps`] in the settings
+const INVULNERABLE_STEPS: [u32; 4] = [0, 2, 4, 8];
+
+pub struct CollisionPlugin;
+
+/// This plugin holds the rules for the head running into the snake's own body
+/// The rules are picked in the settings. Walls and stones are not affected by them.
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CollisionRules::load())
+            .init_resource::<Invulnerability>()
+            .add_observer(activate_rule_button)
+            .add_systems(OnEnter(GameState::Playing), reset_invulnerability)
+            .add_systems(
+                Update,
+                (
+                    save_rules.run_if(
+                        resource_changed::<CollisionRules>
+                            .and(not(resource_added::<CollisionRules>)),
+                    ),
+                    update_rule_text.run_if(resource_changed::<CollisionRules>),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CollisionRules {
+    /// The head may enter the cell of the tail, because the tail moves on with the next step
+    pub tail_safe: bool,
+    /// Steps of the head after growing in which biting the body does no harm
+    pub invulnerable_steps: u32,
+    /// Biting the body cuts off everything behind the bite instead of ending the run
+    pub bite_off_tail: bool,
+}
+
+impl Default for CollisionRules {
+    fn default() -> Self {
+        CollisionRules {
+            tail_safe: true,
+            invulnerable_steps: 0,
+            bite_off_tail: false,
+        }
+    }
+}
+
+/// Outcome of the head sharing a cell with other parts of the snake
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelfCollision {
+    Safe,
+    /// This many parts are cut off the tail end
+    BiteOff(usize),
+    Dead,
+}
+
+impl CollisionRules {
+    fn load() -> Self {
+        storage::read(COLLISION_KEY)
+            .and_then(|rules| Ok(ron::from_str(&rules)?))
+            .unwrap_or_default()
+    }
+
+    /// `bitten` holds the indices of the parts in the cell of the head, counted from the tail of a
+    /// snake with `length` parts including the head
+    pub fn self_collision(
+        &self,
+        bitten: &[usize],
+        length: usize,
+        invulnerable: bool,
+    ) -> SelfCollision {
+        let Some(deepest) = bitten
+            .iter()
+            .copied()
+            .filter(|index| !(self.tail_safe && *index == 0))
+            .max()
+        else {
+            return SelfCollision::Safe;
+        };
+        if invulnerable {
+            return SelfCollision::Safe;
+        }
+        // Everything up to the bitten part goes, as long as a full starting snake is left
+        let cut = deepest + 1;
+        if self.bite_off_tail && length.saturating_sub(cut) >= SNAKE_START_LENGTH {
+            SelfCollision::BiteOff(cut)
+        } else {
+            SelfCollision::Dead
+        }
+    }
+}
+
+fn save_rules(rules: Res<CollisionRules>) {
+    let saved = ron::to_string(&*rules)
+        .map_err(BevyError::from)
+        .and_then(|rules| storage::write(COLLISION_KEY, &rules));
+    if let Err(error) = saved {
+        warn!("Failed to save collision rules: {error}");
+    }
+}
+
+/// Steps of the head that are left without self collisions
+#[derive(Resource, Default)]
+pub struct Invulnerability(pub u32);
+
+impl Invulnerability {
+    /// Called on every step of the head, tells whether this step is protected
+    pub fn step(&mut self) -> bool {
+        let invulnerable = self.0 > 0;
+        self.0 = self.0.saturating_sub(1);
+        invulnerable
+    }
+}
+
+pub fn reset_invulnerability(mut invulnerability: ResMut<Invulnerability>) {
+    invulnerability.0 = 0;
+}
+
+/// Menu button changing one of the [`CollisionRules`]
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum RuleButton {
+    TailSafe,
+    Invulnerability,
+    BiteOff,
+}
+
+/// Label of a [`RuleButton`]
+#[derive(Component)]
+pub struct RuleText(pub RuleButton);
+
+pub fn rule_label(rule: RuleButton, rules: &CollisionRules) -> Localized {
+    match rule {
+        RuleButton::TailSafe => Localized::new(if rules.tail_safe {
+            "tail-safe-on"
+        } else {
+            "tail-safe-off"
+        }),
+        RuleButton::Invulnerability => {
+            Localized::new("invulnerable-steps").with("steps", rules.invulnerable_steps)
+        }
+        RuleButton::BiteOff => Localized::new(if rules.bite_off_tail {
+            "bite-off-on"
+        } else {
+            "bite-off-off"
+        }),
+    }
+}
+
+fn update_rule_text(rules: Res<CollisionRules>, mut texts: Query<(&mut Localized, &RuleText)>) {
+    for (mut text, RuleText(rule)) in &mut texts {
+        text.set_if_neq(rule_label(*rule, &rules));
+    }
+}
+
+fn activate_rule_button(
+    trigger: Trigger<Activate>,
+    buttons: Query<&RuleButton>,
+    mut rules: ResMut<CollisionRules>,
+) {
+    let Ok(rule) = buttons.get(trigger.target()) else {
+        return;
+    };
+    match rule {
+        RuleButton::TailSafe => rules.tail_safe = !rules.tail_safe,
+        RuleButton::Invulnerability => {
+            let index = INVULNERABLE_STEPS
+                .iter()
+                .position(|steps| *steps == rules.invulnerable_steps)
+                .map_or(0, |index| (index + 1) % INVULNERABLE_STEPS.len());
+            rules.invulnerable_steps = INVULNERABLE_STEPS[index];
+        }
+        RuleButton::BiteOff => rules.bite_off_tail = !rules.bite_off_tail,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Long enough to lose a few parts and keep a starting snake
+    const LENGTH: usize = SNAKE_START_LENGTH + 4;
+
+    fn rules(tail_safe: bool, bite_off_tail: bool) -> CollisionRules {
+        CollisionRules {
+            tail_safe,
+            invulnerable_steps: 0,
+            bite_off_tail,
+        }
+    }
+
+    #[test]
+    fn nothing_bitten_is_safe() {
+        for rules in [rules(false, false), rules(true, true)] {
+            assert_eq!(
+                rules.self_collision(&[], LENGTH, false),
+                SelfCollision::Safe
+            );
+        }
+    }
+
+    #[test]
+    fn biting_the_body_ends_the_run_by_default() {
+        let rules = CollisionRules::default();
+        assert_eq!(
+            rules.self_collision(&[2], LENGTH, false),
+            SelfCollision::Dead
+        );
+    }
+
+    #[test]
+    fn tail_cell_is_safe_with_the_rule() {
+        assert_eq!(
+            rules(true, false).self_collision(&[0], LENGTH, false),
+            SelfCollision::Safe
+        );
+    }
+
+    #[test]
+    fn tail_cell_is_deadly_without_the_rule() {
+        assert_eq!(
+            rules(false, false).self_collision(&[0], LENGTH, false),
+            SelfCollision::Dead
+        );
+    }
+
+    #[test]
+    fn safe_tail_does_not_cover_other_parts_in_the_cell() {
+        assert_eq!(
+            rules(true, false).self_collision(&[0, 1], LENGTH, false),
+            SelfCollision::Dead
+        );
+    }
+
+    #[test]
+    fn invulnerable_steps_ignore_bites() {
+        let rules = rules(false, false);
+        assert_eq!(
+            rules.self_collision(&[3], LENGTH, true),
+            SelfCollision::Safe
+        );
+        assert_eq!(
+            rules.self_collision(&[3], LENGTH, false),
+            SelfCollision::Dead
+        );
+    }
+
+    #[test]
+    fn invulnerability_counts_down_with_the_steps() {
+        let mut invulnerability = Invulnerability(2);
+        assert!(invulnerability.step());
+        assert!(invulnerability.step());
+        assert!(!invulnerability.step());
+        assert_eq!(invulnerability.0, 0);
+    }
+
+    #[test]
+    fn bite_off_cuts_up_to_the_bitten_part() {
+        assert_eq!(
+            rules(true, true).self_collision(&[2], LENGTH, false),
+            SelfCollision::BiteOff(3)
+        );
+    }
+
+    #[test]
+    fn bite_off_cuts_behind_the_part_closest_to_the_head() {
+        assert_eq!(
+            rules(false, true).self_collision(&[0, 1], LENGTH, false),
+            SelfCollision::BiteOff(2)
+        );
+    }
+
+    #[test]
+    fn bite_off_keeps_a_starting_snake() {
+        let rules = rules(true, true);
+        let deepest = LENGTH - SNAKE_START_LENGTH - 1;
+        assert_eq!(
+            rules.self_collision(&[deepest], LENGTH, false),
+            SelfCollision::BiteOff(LENGTH - SNAKE_START_LENGTH)
+        );
+        assert_eq!(
+            rules.self_collision(&[deepest + 1], LENGTH, false),
+            SelfCollision::Dead
+        );
+    }
+
+    #[test]
+    fn invulnerability_wins_over_bite_off() {
+        assert_eq!(
+            rules(true, true).self_collision(&[2], LENGTH, true),
+            SelfCollision::Safe
+        );
+    }
+}
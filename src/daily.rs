@@ -0,0 +1,255 @@
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+use std::sync::{Mutex, PoisonError};
+
+use bevy::prelude::*;
+use bevy_rand::{global::GlobalRngEntity, prelude::ChaCha8Rng};
+use serde::{Deserialize, Serialize};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    gems::GemColors,
+    generator::DefaultColors,
+    grid::Walls,
+    menu::setup_menu,
+    mode::GameMode,
+    navigation::Activate,
+    storage,
+    ui::{Explosions, SnakeLength},
+    GamePhase, GameState,
+};
+
+/// Storage key of the [`DailyRecord`]
+const RECORD_KEY: &str = "daily.ron";
+
+/// Storage key the result is written to where there is no clipboard to copy it into
+#[cfg(not(target_family = "wasm"))]
+const SHARE_KEY: &str = "daily_result.txt";
+
+pub struct DailyPlugin;
+
+/// This plugin runs the daily challenge: a board seeded from the date that can be played once per day
+/// Attempts and the streak of consecutive days are kept in local storage
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DailyRecord::load())
+            .add_observer(activate_daily_button)
+            .add_systems(
+                OnEnter(GamePhase::Lost),
+                finish_daily
+                    .run_if(resource_exists::<DailyChallenge>)
+                    .before(setup_menu),
+            )
+            .add_systems(OnEnter(GameState::Restarting), end_daily)
+            .add_systems(OnEnter(GameState::Menu), end_daily);
+    }
+}
+
+/// Present while the daily challenge of the given day is played
+#[derive(Resource, Clone, Copy)]
+pub struct DailyChallenge(pub u64);
+
+/// Days since the unix epoch, in UTC so everyone shares the same challenge
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() / (24 * 60 * 60))
+}
+
+/// Calendar date of a day since the unix epoch as `YYYY-MM-DD`
+fn date(day: u64) -> String {
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day_of_month:02}")
+}
+
+/// Attempts at the daily challenge on this device
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct DailyRecord {
+    /// Day of the last attempt
+    last_day: Option<u64>,
+    /// Days in a row with an attempt, up to `last_day`
+    streak: u32,
+    /// Shareable summary of the last finished attempt
+    result: Option<String>,
+}
+
+impl DailyRecord {
+    fn load() -> Self {
+        storage::read(RECORD_KEY)
+            .and_then(|record| Ok(ron::from_str(&record)?))
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        let saved = ron::to_string(self)
+            .map_err(BevyError::from)
+            .and_then(|record| storage::write(RECORD_KEY, &record));
+        if let Err(error) = saved {
+            warn!("Failed to save daily record: {error}");
+        }
+    }
+
+    pub fn played(&self, day: u64) -> bool {
+        self.last_day == Some(day)
+    }
+
+    /// Streak that is still alive on `day`
+    pub fn streak(&self, day: u64) -> u32 {
+        match self.last_day {
+            Some(last) if last + 1 >= day => self.streak,
+            _ => 0,
+        }
+    }
+
+    /// Summary of the last attempt, if it was made on `day`
+    pub fn result(&self, day: u64) -> Option<&str> {
+        self.result.as_deref().filter(|_| self.played(day))
+    }
+
+    fn start(&mut self, day: u64) {
+        self.streak = if self.last_day.is_some_and(|last| last + 1 == day) {
+            self.streak + 1
+        } else {
+            1
+        };
+        self.last_day = Some(day);
+        self.result = None;
+        self.save();
+    }
+}
+
+/// Menu buttons of the daily challenge
+#[derive(Component)]
+pub enum DailyButton {
+    /// Starts today's challenge, or shares its result once it was played
+    Play,
+    Share,
+}
+
+fn activate_daily_button(
+    trigger: Trigger<Activate>,
+    mut commands: Commands,
+    buttons: Query<&DailyButton>,
+    mut record: ResMut<DailyRecord>,
+    mut rng: GlobalRngEntity<ChaCha8Rng>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Ok(button) = buttons.get(trigger.target()) else {
+        return;
+    };
+    let day = today();
+    match (button, record.result(day)) {
+        (_, Some(result)) => share(result),
+        (DailyButton::Share, None) => (),
+        (DailyButton::Play, None) if record.played(day) => {
+            info!("The daily challenge was already attempted today")
+        }
+        (DailyButton::Play, None) => {
+            info!("Starting daily challenge {}", date(day));
+            record.start(day);
+            let mut seed = [0; 32];
+            seed[..8].copy_from_slice(&day.to_le_bytes());
+            rng.rng_commands().reseed(seed);
+            GameMode::Endless.start(&mut commands);
+            commands.insert_resource(Walls(false));
+            commands.insert_resource(DefaultColors(GemColors::default()));
+            commands.insert_resource(DailyChallenge(day));
+            next_state.set(GameState::Playing);
+        }
+    }
+}
+
+fn finish_daily(
+    challenge: Res<DailyChallenge>,
+    mut record: ResMut<DailyRecord>,
+    explosions: Res<Explosions>,
+    length: Res<SnakeLength>,
+) {
+    let day = challenge.0;
+    let result = format!(
+        "Daily snake {}\nGems destroyed: {}\nSnake length: {}\nStreak: {} {}",
+        date(day),
+        explosions.0,
+        length.0,
+        record.streak(day),
+        if record.streak(day) == 1 {
+            "day"
+        } else {
+            "days"
+        }
+    );
+    info!("Daily challenge finished\n{result}");
+    record.result = Some(result);
+    record.save();
+}
+
+/// The daily seed would make every later run follow the same sequence, so it is replaced with
+/// fresh entropy once the challenge is over
+fn end_daily(
+    mut commands: Commands,
+    challenge: Option<Res<DailyChallenge>>,
+    mut rng: GlobalRngEntity<ChaCha8Rng>,
+) {
+    if challenge.is_some() {
+        rng.rng_commands().reseed_from_local_entropy();
+        commands.remove_resource::<DailyChallenge>();
+    }
+}
+
+#[cfg(target_family = "wasm")]
+fn share(result: &str) {
+    match web_sys::window() {
+        Some(window) => {
+            let _ = window.navigator().clipboard().write_text(result);
+            info!("Copied daily result to the clipboard");
+        }
+        None => warn!("No browser window to copy the daily result from"),
+    }
+}
+
+/// Kept around, because on Linux the copied text is gone once the last clipboard is dropped
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn copy_to_clipboard(text: &str) -> Result<(), arboard::Error> {
+    let mut clipboard = CLIPBOARD.lock().unwrap_or_else(PoisonError::into_inner);
+    let clipboard = match &mut *clipboard {
+        Some(clipboard) => clipboard,
+        None => clipboard.insert(arboard::Clipboard::new()?),
+    };
+    clipboard.set_text(text)
+}
+
+/// Without a clipboard, the result is written to the save directory instead
+#[cfg(not(target_family = "wasm"))]
+fn share(result: &str) {
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    {
+        match copy_to_clipboard(result) {
+            Ok(()) => {
+                info!("Copied daily result to the clipboard");
+                return;
+            }
+            Err(error) => warn!("Failed to copy daily result to the clipboard: {error}"),
+        }
+    }
+    match storage::write(SHARE_KEY, result) {
+        Ok(()) => info!("Wrote daily result to the save directory:\n{result}"),
+        Err(error) => warn!("Failed to write daily result: {error}"),
+    }
+}
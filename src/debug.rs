@@ -0,0 +1,284 @@
+use bevy::{color::palettes::css::YELLOW, prelude::*, time::TimeSystem};
+
+use crate::{
+    board::Exploding,
+    clock::GameClock,
+    grid::{position_to_transform, GRID_HEIGHT, GRID_WIDTH, TILE_SIZE},
+    loading::TextureAssets,
+    player::{ActivePositions, GridPosition, SnakePositions},
+    GamePhase, GameState,
+};
+
+pub struct DebugPlugin;
+
+/// Debug overlay for the grid, the snake and ongoing matches (only part of `dev` builds)
+///
+/// `F3` toggles the overlay, `F4` freezes gameplay and `F5` advances a frozen game by one tick
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugOverlay>()
+            .init_resource::<Stepping>()
+            .add_systems(First, stop_step.after(TimeSystem))
+            .add_systems(
+                Update,
+                (
+                    (toggle_overlay, toggle_stepping, step),
+                    (
+                        draw_coordinates.run_if(resource_changed::<DebugOverlay>),
+                        mark_taken.run_if(
+                            resource_changed::<DebugOverlay>.or(resource_changed::<SnakePositions>),
+                        ),
+                        label_exploding,
+                        update_phase_text,
+                        draw_active.run_if(|overlay: Res<DebugOverlay>| overlay.0),
+                    ),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnEnter(GameState::Playing), refresh_overlay)
+            .add_systems(OnExit(GameState::Playing), remove_overlay);
+    }
+}
+
+#[derive(Resource, Default)]
+struct DebugOverlay(bool);
+
+#[derive(Resource, Default)]
+struct Stepping {
+    enabled: bool,
+    advancing: bool,
+}
+
+/// Everything spawned by the overlay; removed when the overlay is hidden or the game restarts
+#[derive(Component)]
+struct DebugEntity;
+
+#[derive(Component)]
+struct CoordinateLabel;
+
+#[derive(Component)]
+struct ActiveMarker;
+
+#[derive(Component)]
+struct ExplodingLabel;
+
+#[derive(Component)]
+struct PhaseText;
+
+fn refresh_overlay(mut overlay: ResMut<DebugOverlay>) {
+    overlay.set_changed();
+}
+
+fn toggle_overlay(
+    input: Res<ButtonInput<KeyCode>>,
+    mut overlay: ResMut<DebugOverlay>,
+    mut stepping: ResMut<Stepping>,
+    mut clock: ResMut<GameClock>,
+) {
+    if input.just_pressed(KeyCode::F3) {
+        overlay.0 = !overlay.0;
+        if !overlay.0 {
+            stop_stepping(&mut stepping, &mut clock);
+        }
+    }
+}
+
+/// A frozen game must not outlive the overlay
+fn stop_stepping(stepping: &mut Stepping, clock: &mut GameClock) {
+    if stepping.enabled {
+        info!("Stepping mode: false");
+        clock.frozen = false;
+    }
+    *stepping = Stepping::default();
+}
+
+fn toggle_stepping(
+    input: Res<ButtonInput<KeyCode>>,
+    mut stepping: ResMut<Stepping>,
+    mut clock: ResMut<GameClock>,
+) {
+    if input.just_pressed(KeyCode::F4) {
+        stepping.enabled = !stepping.enabled;
+        clock.frozen = stepping.enabled;
+        info!("Stepping mode: {}", stepping.enabled);
+    }
+}
+
+fn step(
+    input: Res<ButtonInput<KeyCode>>,
+    mut stepping: ResMut<Stepping>,
+    mut clock: ResMut<GameClock>,
+) {
+    if stepping.enabled && input.just_pressed(KeyCode::F5) {
+        clock.frozen = false;
+        stepping.advancing = true;
+    }
+}
+
+/// Runs after the time update, so the frame that was just started still gets a delta
+fn stop_step(mut stepping: ResMut<Stepping>, mut clock: ResMut<GameClock>) {
+    if stepping.advancing {
+        clock.frozen = true;
+        stepping.advancing = false;
+    }
+}
+
+fn draw_coordinates(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    labels: Query<Entity, With<CoordinateLabel>>,
+    phase_text: Query<Entity, With<PhaseText>>,
+) {
+    labels
+        .iter()
+        .chain(phase_text.iter())
+        .for_each(|entity| commands.entity(entity).despawn());
+    if !overlay.0 {
+        return;
+    }
+
+    for x in 0..GRID_WIDTH {
+        for y in 0..GRID_HEIGHT {
+            commands.spawn((
+                Text2d::new(format!("{x}/{y}")),
+                TextFont {
+                    font_size: 12.0,
+                    ..default()
+                },
+                Transform::from_translation(
+                    (position_to_transform(&GridPosition { x, y })
+                        + Vec2::new(-TILE_SIZE / 2. + 14., TILE_SIZE / 2. - 8.))
+                    .extend(3.),
+                ),
+                CoordinateLabel,
+                DebugEntity,
+            ));
+        }
+    }
+    commands.spawn((
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        },
+        PhaseText,
+        DebugEntity,
+    ));
+}
+
+fn mark_taken(
+    active: Query<Entity, With<ActiveMarker>>,
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    asset: Res<TextureAssets>,
+    positions: Res<SnakePositions>,
+) {
+    active
+        .iter()
+        .for_each(|entity| commands.entity(entity).despawn());
+    if !overlay.0 {
+        return;
+    }
+
+    for x in 0..GRID_WIDTH {
+        for y in 0..GRID_HEIGHT {
+            let image = match positions.0[x][y].len() {
+                0 => continue,
+                1 => asset.active.clone(),
+                _ => asset._collision.clone(),
+            };
+            commands.spawn((
+                ActiveMarker,
+                DebugEntity,
+                Transform::from_translation(
+                    position_to_transform(&GridPosition { x, y }).extend(0.5),
+                ),
+                Sprite {
+                    image,
+                    color: Color::srgba(1., 1., 1., 0.6),
+                    ..default()
+                },
+            ));
+        }
+    }
+}
+
+fn draw_active(active: Res<ActivePositions>, mut gizmos: Gizmos) {
+    for position in &active.0 {
+        gizmos.rect_2d(
+            Isometry2d::from_translation(position_to_transform(position)),
+            Vec2::splat(TILE_SIZE - 6.),
+            YELLOW,
+        );
+    }
+}
+
+fn label_exploding(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    gems: Query<(Entity, Ref<Exploding>)>,
+    labels: Query<(Entity, &ChildOf), With<ExplodingLabel>>,
+) {
+    if overlay.is_changed() {
+        labels
+            .iter()
+            .for_each(|(entity, _)| commands.entity(entity).despawn());
+    }
+    if !overlay.0 {
+        return;
+    }
+
+    for (gem, exploding) in &gems {
+        if !exploding.is_changed() && !overlay.is_changed() {
+            continue;
+        }
+        // Gems have other children, like their symbol
+        for (label, child_of) in &labels {
+            if child_of.parent() == gem {
+                commands.entity(label).despawn();
+            }
+        }
+        commands.entity(gem).with_child((
+            Text2d::new(exploding.0.to_string()),
+            TextFont {
+                font_size: 24.0,
+                ..default()
+            },
+            TextColor(YELLOW.into()),
+            Transform::from_xyz(0., 0., 3.),
+            ExplodingLabel,
+            DebugEntity,
+        ));
+    }
+}
+
+fn update_phase_text(
+    mut text: Query<&mut Text, With<PhaseText>>,
+    phase: Option<Res<State<GamePhase>>>,
+    stepping: Res<Stepping>,
+) {
+    let Ok(mut text) = text.single_mut() else {
+        return;
+    };
+    let phase = phase.map(|phase| format!("{:?}", phase.get()));
+    **text = format!(
+        "Phase: {}{}",
+        phase.as_deref().unwrap_or("-"),
+        if stepping.enabled { " (stepping)" } else { "" }
+    );
+}
+
+fn remove_overlay(
+    mut commands: Commands,
+    entities: Query<Entity, With<DebugEntity>>,
+    mut stepping: ResMut<Stepping>,
+    mut clock: ResMut<GameClock>,
+) {
+    for entity in entities {
+        commands.entity(entity).despawn();
+    }
+    stop_stepping(&mut stepping, &mut clock);
+}
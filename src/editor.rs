@@ -0,0 +1,447 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_rand::{global::GlobalEntropy, prelude::ChaCha8Rng};
+
+use crate::{
+    accessibility::{gem_symbol, Accessibility},
+    actions::{MoveDirection, Orientation},
+    audio::SoundEffect,
+    board::Tile,
+    gems::{lock_overlay, GemColors, GemType},
+    gravity::GravityShift,
+    grid::{placement, position_to_transform, tile_sprite, transform_to_position, Walls},
+    level::{Level, StartingLevel},
+    loading::TextureAssets,
+    player::GridPosition,
+    skins::SnakeSkin,
+    themes::ActiveTheme,
+    GameState,
+};
+
+pub struct EditorPlugin;
+
+/// This plugin lets designers paint boards and place the snake in `GameState::Editor`
+/// Levels are saved to and loaded from a file and can be played right away
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Editor), setup_editor)
+            .add_systems(
+                Update,
+                (
+                    (
+                        paint,
+                        edit_snake,
+                        edit_board_rules,
+                        edit_gravity_shifts,
+                        save_and_load,
+                        leave_editor,
+                    ),
+                    (draw_gems, draw_snake, update_editor_text)
+                        .run_if(resource_changed::<EditorLevel>),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Editor)),
+            )
+            .add_systems(OnExit(GameState::Editor), cleanup_editor);
+    }
+}
+
+#[derive(Resource)]
+struct EditorLevel {
+    level: Level,
+    brush: Brush,
+    selected_part: usize,
+    /// First half of a portal pair that is being placed, with the tile it replaced
+    pending_portal: Option<(GridPosition, Tile)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Brush {
+    Gem(GemType),
+    Lock,
+    Stone,
+    Hole,
+    Portal,
+}
+
+impl EditorLevel {
+    fn set_tile(&mut self, position: &GridPosition, tile: Tile) {
+        if let Tile::Portal(partner) = &self.level.tiles[position.x][position.y] {
+            self.level.tiles[partner.x][partner.y] = Tile::Gem;
+        }
+        self.level.tiles[position.x][position.y] = tile;
+    }
+
+    /// Puts back the tile under a portal that never got its partner
+    fn cancel_portal(&mut self) {
+        if let Some((position, tile)) = self.pending_portal.take() {
+            // The partner of a replaced portal was already turned back into a gem
+            self.level.tiles[position.x][position.y] = match tile {
+                Tile::Portal(_) => Tile::Gem,
+                tile => tile,
+            };
+        }
+    }
+
+    fn paint(&mut self, position: GridPosition) {
+        match self.brush.clone() {
+            Brush::Gem(gem_type) => {
+                if !self.level.tiles[position.x][position.y].holds_gem() {
+                    self.set_tile(&position, Tile::Gem);
+                }
+                self.level.gems[position.x][position.y] = gem_type;
+            }
+            Brush::Lock => self.set_tile(&position, Tile::LockedGem),
+            Brush::Stone => self.set_tile(&position, Tile::Stone),
+            Brush::Hole => self.set_tile(&position, Tile::Hole),
+            Brush::Portal => match self.pending_portal.take() {
+                Some((entry, _)) if entry != position => {
+                    self.set_tile(&position, Tile::Portal(entry.clone()));
+                    self.level.tiles[entry.x][entry.y] = Tile::Portal(position);
+                }
+                Some(pending) => self.pending_portal = Some(pending),
+                None => {
+                    let tile = self.level.tiles[position.x][position.y].clone();
+                    self.set_tile(&position, Tile::Hole);
+                    self.pending_portal = Some((position, tile));
+                }
+            },
+        }
+    }
+}
+
+/// Everything that is only shown in the editor
+#[derive(Component)]
+struct EditorEntity;
+
+#[derive(Component)]
+struct EditorGem;
+
+#[derive(Component)]
+struct EditorSnake;
+
+#[derive(Component)]
+struct EditorText;
+
+fn setup_editor(
+    mut commands: Commands,
+    mut rng: GlobalEntropy<ChaCha8Rng>,
+    level: Option<Res<StartingLevel>>,
+    walls: Res<Walls>,
+) {
+    let level = match level {
+        Some(level) => level.0.clone(),
+        None => Level::load(&walls).unwrap_or_else(|error| {
+            info!("Starting new level ({error})");
+            Level::random(&mut rng)
+        }),
+    };
+    commands.insert_resource(EditorLevel {
+        level,
+        brush: Brush::Gem(GemType::One),
+        selected_part: 0,
+        pending_portal: None,
+    });
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 15.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        },
+        EditorText,
+        EditorEntity,
+    ));
+}
+
+fn cursor_position(
+    window: &Query<&Window, With<PrimaryWindow>>,
+    camera: &Query<(&Camera, &GlobalTransform)>,
+) -> Option<Vec2> {
+    let cursor = window.single().ok()?.cursor_position()?;
+    let (camera, camera_transform) = camera.single().ok()?;
+
+    camera.viewport_to_world_2d(camera_transform, cursor).ok()
+}
+
+fn paint(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    mut editor: ResMut<EditorLevel>,
+) {
+    for (key, brush) in [
+        (KeyCode::Digit1, Brush::Gem(GemType::One)),
+        (KeyCode::Digit2, Brush::Gem(GemType::Two)),
+        (KeyCode::Digit3, Brush::Gem(GemType::Three)),
+        (KeyCode::Digit4, Brush::Gem(GemType::Four)),
+        (KeyCode::Digit5, Brush::Gem(GemType::Five)),
+        (KeyCode::Digit6, Brush::Gem(GemType::Six)),
+        (KeyCode::Digit7, Brush::Gem(GemType::Seven)),
+        (KeyCode::Digit8, Brush::Lock),
+        (KeyCode::Digit9, Brush::Stone),
+        (KeyCode::Digit0, Brush::Hole),
+        (KeyCode::KeyP, Brush::Portal),
+    ] {
+        if keys.just_pressed(key) {
+            editor.cancel_portal();
+            editor.brush = brush;
+        }
+    }
+
+    // Portals come in pairs, so they are placed one click at a time
+    let painting = if editor.brush == Brush::Portal {
+        mouse.just_pressed(MouseButton::Left)
+    } else {
+        mouse.pressed(MouseButton::Left)
+    };
+    let placing_snake = mouse.just_pressed(MouseButton::Right);
+    if !painting && !placing_snake {
+        return;
+    }
+    let Some(position) = cursor_position(&window, &camera).and_then(transform_to_position) else {
+        return;
+    };
+    if painting {
+        editor.paint(position);
+    } else {
+        editor.level.snake.position = position;
+    }
+}
+
+fn edit_snake(keys: Res<ButtonInput<KeyCode>>, mut editor: ResMut<EditorLevel>) {
+    if keys.just_pressed(KeyCode::KeyR) {
+        let snake = &mut editor.level.snake;
+        snake.orientation = match snake.orientation {
+            Orientation::Up => Orientation::Right,
+            Orientation::Right => Orientation::Down,
+            Orientation::Down => Orientation::Left,
+            Orientation::Left => Orientation::Up,
+        };
+    }
+    if keys.just_pressed(KeyCode::Tab) {
+        editor.selected_part = (editor.selected_part + 1) % editor.level.snake.moves.len();
+    }
+    for (key, direction) in [
+        (KeyCode::ArrowLeft, MoveDirection::Left),
+        (KeyCode::ArrowUp, MoveDirection::Straight),
+        (KeyCode::ArrowRight, MoveDirection::Right),
+    ] {
+        if keys.just_pressed(key) {
+            let selected = editor.selected_part;
+            editor.level.snake.moves[selected] = direction;
+        }
+    }
+}
+
+/// Gems between a new gravity shift and the one before it
+const SHIFT_GEMS: usize = 20;
+/// Gems the brackets move the last gravity shift by
+const SHIFT_STEP: usize = 5;
+
+fn edit_board_rules(keys: Res<ButtonInput<KeyCode>>, mut editor: ResMut<EditorLevel>) {
+    if keys.just_pressed(KeyCode::KeyC) {
+        let count = editor.level.colors.count() + 1;
+        editor.level.colors = GemColors::uniform(if count > GemColors::MAX {
+            GemColors::MIN
+        } else {
+            count
+        });
+    }
+    if keys.just_pressed(KeyCode::KeyG) {
+        editor.level.gravity = editor.level.gravity.next();
+    }
+}
+
+/// Only the last shift is edited, so the shifts stay in order
+fn edit_gravity_shifts(keys: Res<ButtonInput<KeyCode>>, mut editor: ResMut<EditorLevel>) {
+    if !keys.any_just_pressed([
+        KeyCode::KeyK,
+        KeyCode::KeyV,
+        KeyCode::BracketLeft,
+        KeyCode::BracketRight,
+    ]) {
+        return;
+    }
+    let level = &mut editor.level;
+    let shift_held = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if keys.just_pressed(KeyCode::KeyK) && shift_held {
+        level.gravity_shifts.pop();
+    } else if keys.just_pressed(KeyCode::KeyK) {
+        let (after_gems, gravity) = level
+            .gravity_shifts
+            .last()
+            .map_or((0, level.gravity), |shift| {
+                (shift.after_gems, shift.gravity)
+            });
+        level.gravity_shifts.push(GravityShift {
+            after_gems: after_gems + SHIFT_GEMS,
+            gravity: gravity.next(),
+        });
+    }
+    let earliest = match level.gravity_shifts.len() {
+        0 | 1 => 1,
+        len => level.gravity_shifts[len - 2].after_gems + 1,
+    };
+    let Some(shift) = level.gravity_shifts.last_mut() else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::KeyV) {
+        shift.gravity = shift.gravity.next();
+    }
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        shift.after_gems = shift.after_gems.saturating_sub(SHIFT_STEP).max(earliest);
+    }
+    if keys.just_pressed(KeyCode::BracketRight) {
+        shift.after_gems += SHIFT_STEP;
+    }
+}
+
+fn save_and_load(
+    keys: Res<ButtonInput<KeyCode>>,
+    walls: Res<Walls>,
+    mut editor: ResMut<EditorLevel>,
+) {
+    if keys.just_pressed(KeyCode::KeyS) {
+        editor.cancel_portal();
+        match editor.level.save() {
+            Ok(()) => info!("Saved level"),
+            Err(error) => warn!("Failed to save level: {error}"),
+        }
+    }
+    if keys.just_pressed(KeyCode::KeyL) {
+        match Level::load(&walls) {
+            Ok(level) => {
+                editor.level = level;
+                editor.pending_portal = None;
+            }
+            Err(error) => warn!("Failed to load level: {error}"),
+        }
+    }
+}
+
+fn leave_editor(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<EditorLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut writer: EventWriter<SoundEffect>,
+) {
+    if keys.just_pressed(KeyCode::Enter) {
+        editor.cancel_portal();
+        commands.insert_resource(StartingLevel(editor.level.clone()));
+        next_state.set(GameState::Playing);
+        writer.write(SoundEffect::Click);
+    } else if keys.just_pressed(KeyCode::Escape) {
+        commands.remove_resource::<StartingLevel>();
+        next_state.set(GameState::Menu);
+    }
+}
+
+fn draw_gems(
+    mut commands: Commands,
+    editor: Res<EditorLevel>,
+    gems: Query<Entity, With<EditorGem>>,
+    assets: Res<TextureAssets>,
+    accessibility: Res<Accessibility>,
+    theme: Res<ActiveTheme>,
+) {
+    gems.iter()
+        .for_each(|entity| commands.entity(entity).despawn());
+    for (x, column) in editor.level.gems.iter().enumerate() {
+        for (y, gem_type) in column.iter().enumerate() {
+            let tile = &editor.level.tiles[x][y];
+            let mut gem = commands.spawn((
+                Transform::from_translation(
+                    position_to_transform(&GridPosition { x, y }).extend(0.),
+                ),
+                tile_sprite(tile).unwrap_or_else(|| {
+                    Sprite::from_image(assets.gem(gem_type, accessibility.palette, &theme))
+                }),
+                EditorGem,
+                EditorEntity,
+            ));
+            if tile == &Tile::LockedGem {
+                gem.with_child(lock_overlay());
+            }
+            if tile.holds_gem() && accessibility.symbols {
+                gem.with_child(gem_symbol(&assets, gem_type));
+            }
+        }
+    }
+}
+
+fn draw_snake(
+    mut commands: Commands,
+    editor: Res<EditorLevel>,
+    snake: Query<Entity, With<EditorSnake>>,
+    skin: Res<SnakeSkin>,
+) {
+    snake
+        .iter()
+        .for_each(|entity| commands.entity(entity).despawn());
+    let start = &editor.level.snake;
+    let placements = placement(&start.position, start.orientation, &start.moves);
+    let length = placements.len();
+    for (index, (_, direction, transform, _)) in placements.into_iter().enumerate() {
+        let (image, layout) = skin.part(index, length);
+        let mut sprite = Sprite::from_atlas_image(
+            image,
+            TextureAtlas {
+                index: if direction == MoveDirection::Straight {
+                    0
+                } else {
+                    9
+                },
+                layout,
+            },
+        );
+        sprite.flip_x = direction == MoveDirection::Right;
+        if index == editor.selected_part {
+            sprite.color = Color::srgb(1., 0.7, 0.7);
+        }
+        commands.spawn((sprite, transform, EditorSnake, EditorEntity));
+    }
+}
+
+fn update_editor_text(editor: Res<EditorLevel>, mut text: Query<&mut Text, With<EditorText>>) {
+    let Ok(mut text) = text.single_mut() else {
+        return;
+    };
+    let shifts = if editor.level.gravity_shifts.is_empty() {
+        "none".to_string()
+    } else {
+        editor
+            .level
+            .gravity_shifts
+            .iter()
+            .map(|shift| format!("{:?} after {}", shift.gravity, shift.after_gems))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    **text = format!(
+        "Brush: {:?} (1-7 gems, 8 lock, 9 stone, 0 hole, P portal), left click to paint\n\
+        Right click: place tail, R: rotate\n\
+        Tab: select part ({}), arrows: its next move\n\
+        G: gravity ({:?}), C: colours ({})\n\
+        K: add gravity shift, Shift+K: remove it, V: its gravity, [ ]: its gems ({})\n\
+        S: save, L: load, Enter: play, Esc: menu",
+        editor.brush,
+        editor.selected_part + 1,
+        editor.level.gravity,
+        editor.level.colors.count(),
+        shifts
+    );
+}
+
+fn cleanup_editor(mut commands: Commands, entities: Query<Entity, With<EditorEntity>>) {
+    commands.remove_resource::<EditorLevel>();
+    for entity in entities {
+        commands.entity(entity).despawn();
+    }
+}
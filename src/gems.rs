@@ -1,17 +1,36 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 use bevy_rand::{global::GlobalEntropy, prelude::ChaCha8Rng};
-use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    board::{fill_board, Board},
-    grid::{position_to_transform, GRID_HEIGHT, GRID_WIDTH, TILE_SIZE},
+    accessibility::Accessibility,
+    board::{fill_board, Board, Tile},
+    gravity::Gravity,
+    grid::{position_to_transform, TILE_SIZE},
     loading::TextureAssets,
     player::GridPosition,
+    themes::ActiveTheme,
     GamePhase, GameState,
 };
 
 pub struct GemsPlugin;
 
+/// Image of a gem type in the palette of the accessibility settings and the active theme
+#[derive(SystemParam)]
+pub struct GemImages<'w> {
+    textures: Res<'w, TextureAssets>,
+    accessibility: Res<'w, Accessibility>,
+    theme: Res<'w, ActiveTheme>,
+}
+
+impl GemImages<'_> {
+    pub fn get(&self, gem: &GemType) -> Handle<Image> {
+        self.textures
+            .gem(gem, self.accessibility.palette, &self.theme)
+    }
+}
+
 impl Plugin for GemsPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(GameState::Playing), draw_board.after(fill_board))
@@ -59,27 +78,53 @@ fn fall(
 #[derive(Component)]
 pub struct Falling;
 
-fn draw_board(mut commands: Commands, assets: Res<TextureAssets>, mut board: ResMut<Board>) {
-    for x in 0..GRID_WIDTH {
-        for y in 0..GRID_HEIGHT {
+#[derive(Component)]
+pub struct LockOverlay;
+
+/// Shown on top of locked gems until their first match
+pub fn lock_overlay() -> impl Bundle {
+    (
+        LockOverlay,
+        Sprite::from_color(
+            Color::srgba(0.8, 0.8, 0.9, 0.5),
+            Vec2::splat(TILE_SIZE - 12.),
+        ),
+        Transform::from_xyz(0., 0., 0.1),
+    )
+}
+
+pub fn draw_board(
+    mut commands: Commands,
+    gem_images: GemImages,
+    mut board: ResMut<Board>,
+    gravity: Res<Gravity>,
+) {
+    for line in gravity.lines() {
+        let length = line.slots.len();
+        for (index, position) in line.slots.into_iter().enumerate() {
+            let GridPosition { x, y } = position;
+            if !board.tiles[x][y].holds_gem() {
+                continue;
+            }
             let gem_type = board.gems[x][y].gem_type.clone();
-            let position = GridPosition { x, y };
             let id = commands
                 .spawn((
                     Transform::from_translation(
-                        position_to_transform(&position).extend(0.)
-                            + Vec3::new(
-                                0.,
-                                TILE_SIZE * (GRID_HEIGHT + 1) as f32 + y as f32 * TILE_SIZE / 2.,
-                                0.,
-                            ),
+                        (position_to_transform(&position)
+                            + line.inflow
+                                * (TILE_SIZE * (length + 1) as f32
+                                    + index as f32 * TILE_SIZE / 2.))
+                            .extend(0.),
                     ),
-                    Sprite::from_image(assets.gem(&gem_type)),
+                    Sprite::from_image(gem_images.get(&gem_type)),
                     gem_type,
                     position,
                     Falling,
                 ))
                 .id();
+            if board.tiles[x][y] == Tile::LockedGem {
+                commands.entity(id).with_child(lock_overlay());
+            }
             board.gems[x][y].entity = Some(id);
         }
     }
@@ -91,24 +136,78 @@ fn remove_gems(mut commands: Commands, gems: Query<Entity, With<GemType>>) {
     }
 }
 
-#[derive(PartialEq, Eq, Component, Clone)]
+#[derive(PartialEq, Eq, Component, Clone, Debug, Serialize, Deserialize)]
 pub enum GemType {
     One,
     Two,
     Three,
     Four,
     Five,
+    Six,
+    Seven,
 }
 
 impl GemType {
-    pub fn random(rng: &mut GlobalEntropy<ChaCha8Rng>) -> Self {
-        match rng.gen_range(0..5) {
-            0 => GemType::One,
-            1 => GemType::Two,
-            2 => GemType::Three,
-            3 => GemType::Four,
-            4 => GemType::Five,
-            _ => unreachable!(),
+    pub const ALL: [GemType; 7] = [
+        GemType::One,
+        GemType::Two,
+        GemType::Three,
+        GemType::Four,
+        GemType::Five,
+        GemType::Six,
+        GemType::Seven,
+    ];
+
+    /// Position in [`GemType::ALL`]
+    pub fn index(&self) -> usize {
+        self.clone() as usize
+    }
+}
+
+/// Colours that can show up on the board and how often each of them is drawn
+///
+/// There is one weight per active colour, in the order of [`GemType::ALL`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GemColors {
+    pub weights: Vec<u32>,
+}
+
+impl Default for GemColors {
+    fn default() -> Self {
+        GemColors::uniform(5)
+    }
+}
+
+impl GemColors {
+    pub const MIN: usize = 3;
+    pub const MAX: usize = GemType::ALL.len();
+
+    /// The first `count` colours, all with the same chance
+    pub fn uniform(count: usize) -> Self {
+        GemColors {
+            weights: vec![1; count.clamp(GemColors::MIN, GemColors::MAX)],
         }
     }
+
+    pub fn count(&self) -> usize {
+        self.weights.len()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        (GemColors::MIN..=GemColors::MAX).contains(&self.count())
+            && self.weights.iter().any(|weight| *weight > 0)
+    }
+
+    /// Active colours with their weights
+    pub fn gems(&self) -> impl Iterator<Item = (GemType, u32)> + '_ {
+        GemType::ALL.into_iter().zip(self.weights.iter().copied())
+    }
+
+    pub fn distribution(&self) -> WeightedIndex<u32> {
+        WeightedIndex::new(&self.weights).expect("Gem colours are validated before use")
+    }
+
+    pub fn random(&self, rng: &mut GlobalEntropy<ChaCha8Rng>) -> GemType {
+        GemType::ALL[self.distribution().sample(&mut ***rng)].clone()
+    }
 }
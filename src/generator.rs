@@ -0,0 +1,400 @@
+use bevy::prelude::*;
+use bevy_rand::{global::GlobalEntropy, prelude::ChaCha8Rng};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    seq::{IteratorRandom, SliceRandom},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{fill_board, Board},
+    gems::{GemColors, GemType},
+    grid::{GRID_HEIGHT, GRID_WIDTH},
+    level::StartingLevel,
+    locale::Localized,
+    mode::GameMode,
+    navigation::Activate,
+    player::GridPosition,
+    storage, GameState,
+};
+
+/// Storage key of the [`Difficulty`]
+const DIFFICULTY_KEY: &str = "difficulty.ron";
+
+pub struct GeneratorPlugin;
+
+/// This plugin picks the gem generator used to fill the board for a new game
+/// Games outside of levels take their colours from the difficulty picked in the settings.
+impl Plugin for GeneratorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DefaultColors>()
+            .insert_resource(Difficulty::load())
+            .add_observer(activate_difficulty_button)
+            .add_systems(OnEnter(GameState::Menu), apply_difficulty)
+            .add_systems(OnEnter(GameState::ModeSelect), apply_difficulty)
+            .add_systems(
+                Update,
+                (
+                    apply_difficulty.run_if(resource_changed::<Difficulty>),
+                    save_difficulty.run_if(
+                        resource_changed::<Difficulty>.and(not(resource_added::<Difficulty>)),
+                    ),
+                    update_difficulty_text.run_if(resource_changed::<Difficulty>),
+                ),
+            )
+            .add_systems(
+                OnEnter(GameState::Playing),
+                select_generator.before(fill_board),
+            );
+    }
+}
+
+/// Which slots of the board already hold their new gem while filling
+pub type Filled = [[bool; GRID_HEIGHT]; GRID_WIDTH];
+
+/// Decides which gem goes into an empty slot of the board
+pub trait GemGenerator: Send + Sync {
+    fn generate(
+        &mut self,
+        board: &Board,
+        filled: &Filled,
+        position: &GridPosition,
+        rng: &mut GlobalEntropy<ChaCha8Rng>,
+    ) -> GemType;
+
+    /// How likely every active colour currently is, in the order of [`GemType::ALL`]
+    fn weights(&self) -> Vec<u32>;
+}
+
+/// Active colours drawn with their weights
+pub struct RandomGenerator {
+    colors: GemColors,
+    distribution: WeightedIndex<u32>,
+}
+
+impl RandomGenerator {
+    pub fn new(colors: &GemColors) -> Self {
+        RandomGenerator {
+            colors: colors.clone(),
+            distribution: colors.distribution(),
+        }
+    }
+}
+
+impl GemGenerator for RandomGenerator {
+    fn generate(
+        &mut self,
+        _board: &Board,
+        _filled: &Filled,
+        _position: &GridPosition,
+        rng: &mut GlobalEntropy<ChaCha8Rng>,
+    ) -> GemType {
+        GemType::ALL[self.distribution.sample(&mut ***rng)].clone()
+    }
+
+    fn weights(&self) -> Vec<u32> {
+        self.colors.weights.clone()
+    }
+}
+
+/// Biases the weights of the active colours further, one factor per colour
+pub struct WeightedGenerator {
+    weights: Vec<u32>,
+    distribution: WeightedIndex<u32>,
+}
+
+impl WeightedGenerator {
+    /// `None` if there is not one factor per colour or every colour ends up without a chance
+    pub fn new(colors: &GemColors, bias: &[u32]) -> Option<Self> {
+        if bias.len() != colors.count() {
+            return None;
+        }
+        let weights = colors
+            .weights
+            .iter()
+            .zip(bias)
+            .map(|(weight, factor)| weight * factor)
+            .collect::<Vec<_>>();
+        let distribution = WeightedIndex::new(&weights).ok()?;
+
+        Some(WeightedGenerator {
+            weights,
+            distribution,
+        })
+    }
+}
+
+impl GemGenerator for WeightedGenerator {
+    fn generate(
+        &mut self,
+        _board: &Board,
+        _filled: &Filled,
+        _position: &GridPosition,
+        rng: &mut GlobalEntropy<ChaCha8Rng>,
+    ) -> GemType {
+        GemType::ALL[self.distribution.sample(&mut ***rng)].clone()
+    }
+
+    fn weights(&self) -> Vec<u32> {
+        self.weights.clone()
+    }
+}
+
+/// Replaces gems of another generator that would complete three of a kind
+pub struct NoInstantMatchGenerator(pub Box<dyn GemGenerator>);
+
+impl GemGenerator for NoInstantMatchGenerator {
+    fn generate(
+        &mut self,
+        board: &Board,
+        filled: &Filled,
+        position: &GridPosition,
+        rng: &mut GlobalEntropy<ChaCha8Rng>,
+    ) -> GemType {
+        generate_without_match(self.0.as_mut(), board, filled, position, rng)
+    }
+
+    fn weights(&self) -> Vec<u32> {
+        self.0.weights()
+    }
+}
+
+/// A gem of the generator that does not complete a match
+///
+/// A slot rules out at most two colours and there are at least three, so there is always one left.
+/// Gems that match are replaced by one of the other colours, weighted by the generator.
+pub fn generate_without_match(
+    generator: &mut dyn GemGenerator,
+    board: &Board,
+    filled: &Filled,
+    position: &GridPosition,
+    rng: &mut GlobalEntropy<ChaCha8Rng>,
+) -> GemType {
+    let gem_type = generator.generate(board, filled, position, rng);
+    if !board.completes_match(filled, position, &gem_type) {
+        return gem_type;
+    }
+    let candidates = GemType::ALL
+        .into_iter()
+        .zip(generator.weights())
+        .filter(|(candidate, _)| !board.completes_match(filled, position, candidate))
+        .collect::<Vec<_>>();
+    // Colours the generator never draws are still better than a match
+    candidates
+        .choose_weighted(&mut ***rng, |(_, weight)| *weight)
+        .ok()
+        .or_else(|| candidates.iter().choose(&mut ***rng))
+        .map_or(gem_type, |(candidate, _)| candidate.clone())
+}
+
+/// Draws from a shuffled bag holding every active colour as often as its weight and refills it once empty
+pub struct BagGenerator {
+    colors: GemColors,
+    bag: Vec<GemType>,
+}
+
+const BAG_COPIES: usize = 3;
+
+impl BagGenerator {
+    pub fn new(colors: &GemColors) -> Self {
+        BagGenerator {
+            colors: colors.clone(),
+            bag: vec![],
+        }
+    }
+}
+
+impl GemGenerator for BagGenerator {
+    fn generate(
+        &mut self,
+        _board: &Board,
+        _filled: &Filled,
+        _position: &GridPosition,
+        rng: &mut GlobalEntropy<ChaCha8Rng>,
+    ) -> GemType {
+        if self.bag.is_empty() {
+            self.bag = self
+                .colors
+                .gems()
+                .flat_map(|(gem_type, weight)| {
+                    std::iter::repeat_n(gem_type, weight as usize * BAG_COPIES)
+                })
+                .collect();
+            self.bag.shuffle(&mut ***rng);
+        }
+
+        self.bag.pop().expect("Bag was just filled")
+    }
+
+    /// What is left in the bag, or the whole bag once it is empty
+    fn weights(&self) -> Vec<u32> {
+        if self.bag.is_empty() {
+            return self.colors.weights.clone();
+        }
+        self.colors
+            .gems()
+            .map(|(gem_type, _)| self.bag.iter().filter(|gem| **gem == gem_type).count() as u32)
+            .collect()
+    }
+}
+
+/// Serializable choice of a [`GemGenerator`]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GeneratorKind {
+    #[default]
+    Random,
+    NoInstantMatch,
+    /// Factors for the weights of the active colours
+    Weighted(Vec<u32>),
+    Bag,
+}
+
+impl GeneratorKind {
+    pub fn build(&self, colors: &GemColors) -> Box<dyn GemGenerator> {
+        match self {
+            GeneratorKind::Random => Box::new(RandomGenerator::new(colors)),
+            GeneratorKind::NoInstantMatch => Box::new(NoInstantMatchGenerator(Box::new(
+                RandomGenerator::new(colors),
+            ))),
+            GeneratorKind::Weighted(bias) => match WeightedGenerator::new(colors, bias) {
+                Some(generator) => Box::new(generator),
+                None => {
+                    warn!("Invalid gem weights {bias:?}, falling back to random gems");
+                    Box::new(RandomGenerator::new(colors))
+                }
+            },
+            GeneratorKind::Bag => Box::new(BagGenerator::new(colors)),
+        }
+    }
+}
+
+/// Gem colours for games that are not started from a level
+///
+/// These are the colours of the current run. Daily challenges, ghost races and continued runs can
+/// differ from the [`Difficulty`], which is put back once they are over.
+#[derive(Resource, Default)]
+pub struct DefaultColors(pub GemColors);
+
+/// Colours of games outside of levels as picked in the settings, more colours make matches rarer
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Expert,
+}
+
+impl Difficulty {
+    const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Expert,
+    ];
+
+    fn load() -> Self {
+        storage::read(DIFFICULTY_KEY)
+            .and_then(|difficulty| Ok(ron::from_str(&difficulty)?))
+            .unwrap_or_default()
+    }
+
+    pub fn next(self) -> Self {
+        let index = Difficulty::ALL
+            .iter()
+            .position(|difficulty| *difficulty == self)
+            .unwrap_or_default();
+        Difficulty::ALL[(index + 1) % Difficulty::ALL.len()]
+    }
+
+    /// Translation key of the menu label
+    pub fn key(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "difficulty-easy",
+            Difficulty::Normal => "difficulty-normal",
+            Difficulty::Hard => "difficulty-hard",
+            Difficulty::Expert => "difficulty-expert",
+        }
+    }
+
+    /// Hard brings in a sixth colour at half the chance of the others
+    pub fn colors(&self) -> GemColors {
+        match self {
+            Difficulty::Easy => GemColors::uniform(4),
+            Difficulty::Normal => GemColors::default(),
+            Difficulty::Hard => GemColors {
+                weights: vec![2, 2, 2, 2, 2, 1],
+            },
+            Difficulty::Expert => GemColors::uniform(GemColors::MAX),
+        }
+    }
+}
+
+fn apply_difficulty(difficulty: Res<Difficulty>, mut colors: ResMut<DefaultColors>) {
+    colors.0 = difficulty.colors();
+}
+
+fn save_difficulty(difficulty: Res<Difficulty>) {
+    let saved = ron::to_string(&*difficulty)
+        .map_err(BevyError::from)
+        .and_then(|difficulty| storage::write(DIFFICULTY_KEY, &difficulty));
+    if let Err(error) = saved {
+        warn!("Failed to save difficulty: {error}");
+    }
+}
+
+/// Menu button cycling through the [`Difficulty`]
+#[derive(Component)]
+pub struct DifficultyButton;
+
+/// Label of the [`DifficultyButton`]
+#[derive(Component)]
+pub struct DifficultyText;
+
+pub fn difficulty_label(difficulty: &Difficulty) -> Localized {
+    Localized::new(difficulty.key())
+}
+
+fn update_difficulty_text(
+    difficulty: Res<Difficulty>,
+    mut texts: Query<&mut Localized, With<DifficultyText>>,
+) {
+    for mut text in &mut texts {
+        text.set_if_neq(difficulty_label(&difficulty));
+    }
+}
+
+fn activate_difficulty_button(
+    trigger: Trigger<Activate>,
+    buttons: Query<(), With<DifficultyButton>>,
+    mut difficulty: ResMut<Difficulty>,
+) {
+    if buttons.contains(trigger.target()) {
+        *difficulty = difficulty.next();
+    }
+}
+
+/// Generator refilling the board in the current game
+#[derive(Resource)]
+pub struct ActiveGenerator(pub Box<dyn GemGenerator>);
+
+fn select_generator(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    default_colors: Res<DefaultColors>,
+    level: Option<Res<StartingLevel>>,
+) {
+    let (kind, mut colors) = match level {
+        Some(level) => (level.0.generator.clone(), level.0.colors.clone()),
+        None => (mode.generator(), default_colors.0.clone()),
+    };
+    if !colors.is_valid() {
+        warn!("Invalid gem colours {colors:?}, falling back to the default colours");
+        colors = GemColors::default();
+    }
+    info!(
+        "Using {kind:?} gem generator with {} colours",
+        colors.count()
+    );
+    commands.insert_resource(ActiveGenerator(kind.build(&colors)));
+}
@@ -0,0 +1,414 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_rand::{
+    global::GlobalEntropy,
+    prelude::{ChaCha8Rng, Entropy},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::Orientation,
+    board::fill_board,
+    following::Trailing,
+    gems::GemColors,
+    generator::DefaultColors,
+    gravity::{reset_gravity, Gravity},
+    grid::{position_to_transform, Walls},
+    level::StartingLevel,
+    mode::GameMode,
+    navigation::Activate,
+    player::{spawn_player, GridPosition, SnakeHead, SnakeTail},
+    save::{restore_run, ResumedRun},
+    skins::SnakeSkin,
+    storage,
+    ui::{Explosions, SnakeLength},
+    GamePhase, GameState,
+};
+
+/// Storage key of the [`Ghosts`]
+const GHOSTS_KEY: &str = "ghosts.ron";
+
+const GHOST_COLOR: Color = Color::srgba(1., 1., 1., 0.35);
+
+pub struct GhostPlugin;
+
+/// This plugin records the path of the snake's head and keeps the best run of every mode
+/// A new run starting from the same random seed shows that run as a translucent ghost snake to race
+/// against. The ghost has no grid position, so it never collides with anything or matches gems.
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Ghosts::load())
+            .init_resource::<RaceClock>()
+            .add_observer(activate_race_button)
+            .add_systems(
+                OnEnter(GameState::Playing),
+                (
+                    restore_race_seed.run_if(resource_exists::<GhostRace>),
+                    start_recording,
+                    summon_ghost,
+                )
+                    .chain()
+                    .run_if(not(resource_exists::<ResumedRun>))
+                    .after(reset_gravity)
+                    .before(spawn_player)
+                    .before(fill_board),
+            )
+            .add_systems(OnEnter(GameState::Playing), reset_clock)
+            .add_systems(
+                OnEnter(GameState::Playing),
+                record_start
+                    .after(spawn_player)
+                    .after(restore_run)
+                    .run_if(resource_exists::<Recording>),
+            )
+            .add_systems(OnExit(GameState::Playing), (stop_recording, banish_ghost))
+            .add_systems(OnEnter(GameState::Menu), end_race)
+            .add_systems(
+                Update,
+                (
+                    tick_clock,
+                    record_steps.run_if(resource_exists::<Recording>),
+                )
+                    .chain()
+                    .run_if(
+                        in_state(GamePhase::Playing)
+                            .or(in_state(GamePhase::Exploding))
+                            .or(in_state(GamePhase::Waiting)),
+                    ),
+            )
+            .add_systems(
+                Update,
+                move_ghost
+                    .after(tick_clock)
+                    .run_if(resource_exists::<ActiveGhost>),
+            )
+            .add_systems(
+                OnEnter(GamePhase::Lost),
+                keep_best_run.run_if(resource_exists::<Recording>),
+            );
+    }
+}
+
+/// Position of the head after one step
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct GhostStep {
+    /// Time since the start of the run
+    time: Duration,
+    position: GridPosition,
+    orientation: Orientation,
+    /// Length of the snake at this step
+    length: usize,
+}
+
+/// A finished run that can be raced against
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GhostRun {
+    /// State of the random number generator when the run started
+    seed: Entropy<ChaCha8Rng>,
+    walls: bool,
+    #[serde(default)]
+    colors: GemColors,
+    #[serde(default)]
+    gravity: Gravity,
+    score: usize,
+    steps: Vec<GhostStep>,
+}
+
+/// The best run of every mode
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct Ghosts(BTreeMap<GameMode, GhostRun>);
+
+impl Ghosts {
+    fn load() -> Self {
+        storage::read(GHOSTS_KEY)
+            .and_then(|ghosts| Ok(ron::from_str(&ghosts)?))
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        let saved = ron::to_string(self)
+            .map_err(BevyError::from)
+            .and_then(|ghosts| storage::write(GHOSTS_KEY, &ghosts));
+        if let Err(error) = saved {
+            warn!("Failed to save ghosts: {error}");
+        }
+    }
+
+    /// Whether there is a run to race against in the mode
+    pub fn contains(&self, mode: GameMode) -> bool {
+        self.0.contains_key(&mode)
+    }
+}
+
+/// Present while racing against the ghost of a mode, restarting keeps racing
+#[derive(Resource)]
+struct GhostRace(GameMode);
+
+/// Time the snake was in play during the current run
+#[derive(Resource, Default)]
+pub struct RaceClock(Duration);
+
+/// The run that is being played
+#[derive(Resource)]
+struct Recording(GhostRun);
+
+/// Recording of a run as it is kept in a save, so a continued run can still become the ghost
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedRecording {
+    run: GhostRun,
+    clock: Duration,
+}
+
+impl SavedRecording {
+    /// Has to happen after [`reset_clock`]
+    pub fn restore(&self, commands: &mut Commands) {
+        commands.insert_resource(Recording(self.run.clone()));
+        commands.insert_resource(RaceClock(self.clock));
+    }
+}
+
+/// Everything about the recording that a save records
+#[derive(SystemParam)]
+pub struct RecordingState<'w> {
+    recording: Option<Res<'w, Recording>>,
+    clock: Res<'w, RaceClock>,
+}
+
+impl RecordingState<'_> {
+    /// Levels are not recorded
+    pub fn save(&self) -> Option<SavedRecording> {
+        self.recording.as_ref().map(|recording| SavedRecording {
+            run: recording.0.clone(),
+            clock: self.clock.0,
+        })
+    }
+}
+
+/// The ghost shown in the current run
+#[derive(Resource)]
+struct ActiveGhost {
+    steps: Vec<GhostStep>,
+    /// Index of the step the ghost is at
+    current: Option<usize>,
+}
+
+#[derive(Component)]
+struct GhostPart;
+
+/// Menu button starting a race against the ghost of the mode
+#[derive(Component)]
+pub struct RaceButton(pub GameMode);
+
+fn activate_race_button(
+    trigger: Trigger<Activate>,
+    mut commands: Commands,
+    buttons: Query<&RaceButton>,
+    ghosts: Res<Ghosts>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Ok(RaceButton(mode)) = buttons.get(trigger.target()) else {
+        return;
+    };
+    let Some(ghost) = ghosts.0.get(mode) else {
+        return;
+    };
+    mode.start(&mut commands);
+    commands.insert_resource(Walls(ghost.walls));
+    commands.insert_resource(DefaultColors(ghost.colors.clone()));
+    commands.insert_resource(GhostRace(*mode));
+    next_state.set(GameState::Playing);
+}
+
+fn end_race(mut commands: Commands) {
+    commands.remove_resource::<GhostRace>();
+}
+
+/// The board and the snake come out the same as in the ghost's run
+fn restore_race_seed(
+    race: Res<GhostRace>,
+    ghosts: Res<Ghosts>,
+    mut rng: GlobalEntropy<ChaCha8Rng>,
+    mut gravity: ResMut<Gravity>,
+) {
+    if let Some(ghost) = ghosts.0.get(&race.0) {
+        **rng = ghost.seed.clone();
+        *gravity = ghost.gravity;
+    }
+}
+
+/// Levels are left out, their boards do not come from the seed
+fn start_recording(
+    mut commands: Commands,
+    rng: GlobalEntropy<ChaCha8Rng>,
+    walls: Res<Walls>,
+    colors: Res<DefaultColors>,
+    gravity: Res<Gravity>,
+    level: Option<Res<StartingLevel>>,
+) {
+    if level.is_some() {
+        return;
+    }
+    commands.insert_resource(Recording(GhostRun {
+        seed: (*rng).clone(),
+        walls: walls.0,
+        colors: colors.0.clone(),
+        gravity: *gravity,
+        score: 0,
+        steps: vec![],
+    }));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn summon_ghost(
+    mut commands: Commands,
+    rng: GlobalEntropy<ChaCha8Rng>,
+    ghosts: Res<Ghosts>,
+    mode: Res<GameMode>,
+    walls: Res<Walls>,
+    colors: Res<DefaultColors>,
+    gravity: Res<Gravity>,
+    level: Option<Res<StartingLevel>>,
+) {
+    let Some(ghost) = ghosts.0.get(&*mode) else {
+        return;
+    };
+    if level.is_some()
+        || ghost.walls != walls.0
+        || ghost.colors != colors.0
+        || ghost.gravity != *gravity
+        || ghost.seed != **rng
+    {
+        return;
+    }
+    info!("Racing against a ghost with a score of {}", ghost.score);
+    commands.insert_resource(ActiveGhost {
+        steps: ghost.steps.clone(),
+        current: None,
+    });
+}
+
+fn stop_recording(mut commands: Commands) {
+    commands.remove_resource::<Recording>();
+}
+
+fn banish_ghost(mut commands: Commands, parts: Query<Entity, With<GhostPart>>) {
+    commands.remove_resource::<ActiveGhost>();
+    for part in &parts {
+        commands.entity(part).despawn();
+    }
+}
+
+pub fn reset_clock(mut clock: ResMut<RaceClock>) {
+    clock.0 = Duration::ZERO;
+}
+
+fn tick_clock(time: Res<Time>, mut clock: ResMut<RaceClock>) {
+    clock.0 += time.delta();
+}
+
+/// The whole snake at the start, so the ghost starts out with its full length
+///
+/// A continued run already recorded its start.
+fn record_start(
+    mut recording: ResMut<Recording>,
+    length: Res<SnakeLength>,
+    tail: Query<Entity, With<SnakeTail>>,
+    parts: Query<(&GridPosition, &Orientation, Option<&Trailing>)>,
+) -> Result {
+    let steps = &mut recording.0.steps;
+    if !steps.is_empty() {
+        return Ok(());
+    }
+    let mut next = Some(tail.single()?);
+    while let Some(entity) = next {
+        let (position, orientation, trailing) = parts.get(entity)?;
+        steps.push(GhostStep {
+            time: Duration::ZERO,
+            position: position.clone(),
+            orientation: *orientation,
+            length: length.0,
+        });
+        next = trailing.map(|trailing| trailing.0);
+    }
+
+    Ok(())
+}
+
+fn record_steps(
+    mut recording: ResMut<Recording>,
+    clock: Res<RaceClock>,
+    length: Res<SnakeLength>,
+    head: Query<(&GridPosition, &Orientation), (With<SnakeHead>, Changed<GridPosition>)>,
+) {
+    let Ok((position, orientation)) = head.single() else {
+        return;
+    };
+    let steps = &mut recording.0.steps;
+    // A head that was just spawned has not taken a step yet
+    if steps.last().is_some_and(|last| last.position == *position) {
+        return;
+    }
+    steps.push(GhostStep {
+        time: clock.0,
+        position: position.clone(),
+        orientation: *orientation,
+        length: length.0,
+    });
+}
+
+fn keep_best_run(
+    mut recording: ResMut<Recording>,
+    mut ghosts: ResMut<Ghosts>,
+    mode: Res<GameMode>,
+    explosions: Res<Explosions>,
+    length: Res<SnakeLength>,
+) {
+    let score = mode.score(&explosions, &length);
+    if ghosts.0.get(&*mode).is_some_and(|best| best.score >= score) || score == 0 {
+        return;
+    }
+    info!("Keeping the run as the new ghost of {}", mode.name());
+    recording.0.score = score;
+    ghosts.0.insert(*mode, recording.0.clone());
+    ghosts.save();
+}
+
+/// Shows the ghost at the last step it had taken at this point of its run
+fn move_ghost(
+    mut commands: Commands,
+    mut ghost: ResMut<ActiveGhost>,
+    clock: Res<RaceClock>,
+    skin: Res<SnakeSkin>,
+    parts: Query<Entity, With<GhostPart>>,
+) {
+    let reached = ghost.steps.partition_point(|step| step.time <= clock.0);
+    let current = reached.checked_sub(1);
+    if current == ghost.current {
+        return;
+    }
+    ghost.current = current;
+    for part in &parts {
+        commands.entity(part).despawn();
+    }
+    // After its last step the ghost stays where its run ended
+    let Some(current) = current else {
+        return;
+    };
+
+    let length = ghost.steps[current].length.min(current + 1);
+    for (index, step) in ghost.steps[current + 1 - length..=current]
+        .iter()
+        .enumerate()
+    {
+        let (image, layout) = skin.part(index, length);
+        let mut sprite = Sprite::from_atlas_image(image, TextureAtlas { layout, index: 0 });
+        sprite.color = GHOST_COLOR;
+        commands.spawn((
+            sprite,
+            Transform::from_translation(position_to_transform(&step.position).extend(0.9))
+                .with_rotation(Quat::from_rotation_z(step.orientation.z_angle())),
+            GhostPart,
+        ));
+    }
+}
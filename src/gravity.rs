@@ -0,0 +1,233 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::fill_board,
+    daily::DailyChallenge,
+    grid::{GRID_HEIGHT, GRID_WIDTH},
+    level::StartingLevel,
+    locale::Localized,
+    navigation::Activate,
+    player::GridPosition,
+    storage,
+    ui::Explosions,
+    GameState,
+};
+
+/// Storage key of the [`GravitySetting`]
+const GRAVITY_KEY: &str = "gravity.ron";
+
+pub struct GravityPlugin;
+
+/// This plugin keeps track of the direction gems fall in when the board is refilled
+/// Levels bring their own gravity and shifts of it, other games use the one from the settings.
+impl Plugin for GravityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Gravity>()
+            .insert_resource(GravitySetting::load())
+            .add_observer(activate_gravity_button)
+            .add_systems(
+                OnEnter(GameState::Playing),
+                reset_gravity.before(fill_board),
+            )
+            .add_systems(
+                Update,
+                (
+                    save_gravity.run_if(
+                        resource_changed::<GravitySetting>
+                            .and(not(resource_added::<GravitySetting>)),
+                    ),
+                    update_gravity_text.run_if(resource_changed::<GravitySetting>),
+                ),
+            )
+            .add_systems(
+                Update,
+                shift_gravity
+                    .run_if(resource_exists::<StartingLevel>)
+                    .run_if(resource_changed::<Explosions>)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Gravity {
+    #[default]
+    Down,
+    Up,
+    Left,
+    Right,
+    /// Each column falls towards its middle
+    Center,
+}
+
+/// Slots that gems fall along, starting at the floor
+pub struct Line {
+    pub slots: Vec<GridPosition>,
+    /// Direction new gems come in from
+    pub inflow: Vec2,
+}
+
+/// Switch gravity once enough gems were destroyed in a level
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GravityShift {
+    pub after_gems: usize,
+    pub gravity: Gravity,
+}
+
+impl Gravity {
+    /// The gravity after this one when cycling through all of them
+    pub fn next(self) -> Self {
+        match self {
+            Gravity::Down => Gravity::Left,
+            Gravity::Left => Gravity::Up,
+            Gravity::Up => Gravity::Right,
+            Gravity::Right => Gravity::Center,
+            Gravity::Center => Gravity::Down,
+        }
+    }
+
+    /// Translation key of the name
+    fn key(&self) -> &'static str {
+        match self {
+            Gravity::Down => "gravity-down",
+            Gravity::Up => "gravity-up",
+            Gravity::Left => "gravity-left",
+            Gravity::Right => "gravity-right",
+            Gravity::Center => "gravity-center",
+        }
+    }
+
+    pub fn lines(&self) -> Vec<Line> {
+        let column = |x: usize, ys: &mut dyn Iterator<Item = usize>| {
+            ys.map(|y| GridPosition { x, y }).collect::<Vec<_>>()
+        };
+        let row = |y: usize, xs: &mut dyn Iterator<Item = usize>| {
+            xs.map(|x| GridPosition { x, y }).collect::<Vec<_>>()
+        };
+        match self {
+            Gravity::Down => (0..GRID_WIDTH)
+                .map(|x| Line {
+                    slots: column(x, &mut (0..GRID_HEIGHT)),
+                    inflow: Vec2::Y,
+                })
+                .collect(),
+            Gravity::Up => (0..GRID_WIDTH)
+                .map(|x| Line {
+                    slots: column(x, &mut (0..GRID_HEIGHT).rev()),
+                    inflow: -Vec2::Y,
+                })
+                .collect(),
+            Gravity::Left => (0..GRID_HEIGHT)
+                .map(|y| Line {
+                    slots: row(y, &mut (0..GRID_WIDTH)),
+                    inflow: Vec2::X,
+                })
+                .collect(),
+            Gravity::Right => (0..GRID_HEIGHT)
+                .map(|y| Line {
+                    slots: row(y, &mut (0..GRID_WIDTH).rev()),
+                    inflow: -Vec2::X,
+                })
+                .collect(),
+            Gravity::Center => (0..GRID_WIDTH)
+                .flat_map(|x| {
+                    [
+                        Line {
+                            slots: column(x, &mut (GRID_HEIGHT / 2..GRID_HEIGHT)),
+                            inflow: Vec2::Y,
+                        },
+                        Line {
+                            slots: column(x, &mut (0..GRID_HEIGHT / 2).rev()),
+                            inflow: -Vec2::Y,
+                        },
+                    ]
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Gravity picked in the settings
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GravitySetting(pub Gravity);
+
+impl GravitySetting {
+    fn load() -> Self {
+        storage::read(GRAVITY_KEY)
+            .and_then(|gravity| Ok(ron::from_str(&gravity)?))
+            .unwrap_or_default()
+    }
+}
+
+fn save_gravity(gravity: Res<GravitySetting>) {
+    let saved = ron::to_string(&*gravity)
+        .map_err(BevyError::from)
+        .and_then(|gravity| storage::write(GRAVITY_KEY, &gravity));
+    if let Err(error) = saved {
+        warn!("Failed to save gravity: {error}");
+    }
+}
+
+/// The daily challenge is the same for everyone, so it ignores the setting
+pub fn reset_gravity(
+    mut gravity: ResMut<Gravity>,
+    setting: Res<GravitySetting>,
+    level: Option<Res<StartingLevel>>,
+    daily: Option<Res<DailyChallenge>>,
+) {
+    *gravity = match (level, daily) {
+        (Some(level), _) => level.0.gravity,
+        (None, Some(_)) => Gravity::default(),
+        (None, None) => setting.0,
+    };
+}
+
+fn shift_gravity(
+    mut gravity: ResMut<Gravity>,
+    level: Res<StartingLevel>,
+    explosions: Res<Explosions>,
+) {
+    let next = level
+        .0
+        .gravity_shifts
+        .iter()
+        .filter(|shift| shift.after_gems <= explosions.0)
+        .max_by_key(|shift| shift.after_gems)
+        .map_or(level.0.gravity, |shift| shift.gravity);
+    if *gravity != next {
+        info!("Gravity is now {next:?}");
+        *gravity = next;
+    }
+}
+
+/// Menu button cycling through the gravities
+#[derive(Component)]
+pub struct GravityButton;
+
+/// Label of the [`GravityButton`]
+#[derive(Component)]
+pub struct GravityText;
+
+pub fn gravity_label(setting: &GravitySetting) -> Localized {
+    Localized::new(setting.0.key())
+}
+
+fn update_gravity_text(
+    setting: Res<GravitySetting>,
+    mut texts: Query<&mut Localized, With<GravityText>>,
+) {
+    for mut text in &mut texts {
+        text.set_if_neq(gravity_label(&setting));
+    }
+}
+
+fn activate_gravity_button(
+    trigger: Trigger<Activate>,
+    buttons: Query<(), With<GravityButton>>,
+    mut setting: ResMut<GravitySetting>,
+) {
+    if buttons.contains(trigger.target()) {
+        setting.0 = setting.0.next();
+    }
+}
@@ -4,8 +4,10 @@ use rand::Rng;
 
 use crate::{
     actions::{MoveDirection, NextMove, Orientation},
-    loading::TextureAssets,
+    board::{fill_board, Board, Tile},
     player::GridPosition,
+    save::restore_run,
+    themes::ActiveTheme,
     GameState,
 };
 
@@ -14,41 +16,127 @@ pub struct GridPlugin;
 pub const GRID_WIDTH: usize = 12;
 pub const GRID_HEIGHT: usize = 8;
 pub const TILE_SIZE: f32 = 64.;
+pub const WALL_THICKNESS: f32 = 8.;
+/// Random snakes tried before falling back to a straight one that never reaches the border
+const PLACEMENT_TRIES: usize = 100;
+
+/// With walls, the snake dies at the border instead of wrapping around to the other side
+///
+/// This is what the current run plays with. Modes, daily challenges, ghost races and continued runs
+/// can differ from the [`WallsSetting`], which is put back once they are over.
+#[derive(Resource, Default)]
+pub struct Walls(pub bool);
+
+/// Walls as picked in the settings
+#[derive(Resource, Default)]
+pub struct WallsSetting(pub bool);
 
 impl Plugin for GridPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(EntropyPlugin::<ChaCha8Rng>::default())
+            .init_resource::<Walls>()
+            .init_resource::<WallsSetting>()
+            .add_systems(OnEnter(GameState::Menu), apply_walls_setting)
+            .add_systems(OnEnter(GameState::ModeSelect), apply_walls_setting)
+            .add_systems(
+                Update,
+                apply_walls_setting.run_if(resource_changed::<WallsSetting>),
+            )
             .add_systems(OnEnter(GameState::Playing), spawn_grid)
-            .add_systems(OnEnter(GameState::Restarting), remove_grid);
+            .add_systems(
+                OnEnter(GameState::Playing),
+                // A continued run brings its own board
+                draw_special_tiles.after(fill_board).after(restore_run),
+            )
+            .add_systems(OnEnter(GameState::Restarting), remove_grid)
+            .add_systems(OnEnter(GameState::Editor), spawn_grid)
+            .add_systems(OnExit(GameState::Editor), remove_grid);
     }
 }
 
+fn apply_walls_setting(setting: Res<WallsSetting>, mut walls: ResMut<Walls>) {
+    walls.0 = setting.0;
+}
+
 pub fn random_placement(
     length: u8,
+    walls: &Walls,
     rng: &mut GlobalEntropy<ChaCha8Rng>,
 ) -> Vec<(Orientation, MoveDirection, Transform, GridPosition)> {
-    let mut placements = vec![];
+    for _ in 0..PLACEMENT_TRIES {
+        let placements = random_moves_placement(length, rng);
+        if !walls.0 || !crosses_border(&placements) {
+            return placements;
+        }
+    }
+
+    warn!("No random snake fits between the walls, starting straight");
+    placement(
+        &start_position(),
+        Orientation::Up,
+        &vec![MoveDirection::Straight; length as usize],
+    )
+}
+
+/// Whether any part of the placement has to wrap around the board to get to the next one
+pub fn crosses_border(
+    placements: &[(Orientation, MoveDirection, Transform, GridPosition)],
+) -> bool {
+    placements
+        .iter()
+        .rev()
+        .skip(1)
+        .any(|(orientation, direction, _, position)| {
+            let mut orientation = *orientation;
+            orientation.next(&NextMove(*direction));
+            orientation.crosses_border(position)
+        })
+}
 
+fn random_moves_placement(
+    length: u8,
+    rng: &mut GlobalEntropy<ChaCha8Rng>,
+) -> Vec<(Orientation, MoveDirection, Transform, GridPosition)> {
     let curves = [rng.gen_range(0..length - 1), rng.gen_range(0..length - 1)];
+    let moves = (0..length)
+        .map(|i| {
+            if curves.contains(&i) {
+                if rng.gen_bool(0.5) {
+                    MoveDirection::Left
+                } else {
+                    MoveDirection::Right
+                }
+            } else {
+                MoveDirection::Straight
+            }
+        })
+        .collect::<Vec<_>>();
+
+    placement(&start_position(), Orientation::Up, &moves)
+}
 
-    let mut next_orientation = Orientation::Up;
-    let mut next_grid_position = GridPosition {
+fn start_position() -> GridPosition {
+    GridPosition {
         x: GRID_WIDTH / 2,
         y: GRID_HEIGHT / 2,
-    };
-    let mut next_position = Vec3::new(TILE_SIZE / 2., 0., 1.);
-    let mut next_rotation = 0.;
-    for i in 0..length {
-        let direction = if curves.contains(&i) {
-            if rng.gen_bool(0.5) {
-                MoveDirection::Left
-            } else {
-                MoveDirection::Right
-            }
-        } else {
-            MoveDirection::Straight
-        };
+    }
+}
 
+/// Lay out snake parts starting with the tail at `start`
+///
+/// Every part takes the next move in `moves` to get to the position of the following part.
+pub fn placement(
+    start: &GridPosition,
+    orientation: Orientation,
+    moves: &[MoveDirection],
+) -> Vec<(Orientation, MoveDirection, Transform, GridPosition)> {
+    let mut placements = vec![];
+
+    let mut next_orientation = orientation;
+    let mut next_grid_position = start.clone();
+    let mut next_position = position_to_transform(start).extend(1.);
+    let mut next_rotation = orientation.z_angle();
+    for direction in moves.iter().copied() {
         let mut transform = Transform::from_translation(next_position);
         transform.rotate_z(next_rotation);
         placements.push((
@@ -81,6 +169,19 @@ pub fn wrap_translate(translate: &mut Vec3) {
     }
 }
 
+pub fn transform_to_position(translation: Vec2) -> Option<GridPosition> {
+    let x = (translation.x / TILE_SIZE + GRID_WIDTH as f32 / 2.).floor();
+    let y = (translation.y / TILE_SIZE + GRID_HEIGHT as f32 / 2. + 0.5).floor();
+    if x < 0. || y < 0. || x >= GRID_WIDTH as f32 || y >= GRID_HEIGHT as f32 {
+        return None;
+    }
+
+    Some(GridPosition {
+        x: x as usize,
+        y: y as usize,
+    })
+}
+
 pub fn position_to_transform(position: &GridPosition) -> Vec2 {
     Vec2::new(
         (-(GRID_WIDTH as f32) / 2. + position.x as f32 + 0.5) * TILE_SIZE,
@@ -91,17 +192,81 @@ pub fn position_to_transform(position: &GridPosition) -> Vec2 {
 #[derive(Component)]
 struct GridTile;
 
-fn spawn_grid(mut commands: Commands, textures: Res<TextureAssets>) {
+/// Floor of a cell, drawn with the tile of the active theme
+#[derive(Component)]
+pub struct FloorTile;
+
+fn spawn_grid(mut commands: Commands, theme: Res<ActiveTheme>, walls: Res<Walls>) {
     for column in 0..GRID_WIDTH {
         for row in 1..=GRID_HEIGHT {
             commands.spawn((
-                Sprite::from_image(textures.tile.clone()),
+                Sprite::from_image(theme.tile()),
                 Transform::from_translation(Vec3::new(
                     (-(GRID_WIDTH as f32) / 2. + column as f32 + 0.5) * TILE_SIZE,
                     (GRID_HEIGHT as f32 / 2. - row as f32) * TILE_SIZE,
                     0.,
                 )),
                 GridTile,
+                FloorTile,
+            ));
+        }
+    }
+
+    if walls.0 {
+        let width = GRID_WIDTH as f32 * TILE_SIZE;
+        let height = GRID_HEIGHT as f32 * TILE_SIZE;
+        let center = Vec2::new(0., -TILE_SIZE / 2.);
+        for (offset, size) in [
+            (
+                Vec2::new(0., (height + WALL_THICKNESS) / 2.),
+                Vec2::new(width + 2. * WALL_THICKNESS, WALL_THICKNESS),
+            ),
+            (
+                Vec2::new(0., -(height + WALL_THICKNESS) / 2.),
+                Vec2::new(width + 2. * WALL_THICKNESS, WALL_THICKNESS),
+            ),
+            (
+                Vec2::new((width + WALL_THICKNESS) / 2., 0.),
+                Vec2::new(WALL_THICKNESS, height),
+            ),
+            (
+                Vec2::new(-(width + WALL_THICKNESS) / 2., 0.),
+                Vec2::new(WALL_THICKNESS, height),
+            ),
+        ] {
+            commands.spawn((
+                Sprite::from_color(Color::srgb(0.2, 0.2, 0.22), size),
+                Transform::from_translation((center + offset).extend(0.)),
+                GridTile,
+            ));
+        }
+    }
+}
+
+/// Sprite for cells that do not hold a plain gem
+pub fn tile_sprite(tile: &Tile) -> Option<Sprite> {
+    let color = match tile {
+        Tile::Gem | Tile::LockedGem => return None,
+        Tile::Stone => Color::srgb(0.35, 0.35, 0.38),
+        Tile::Hole => Color::srgb(0.1, 0.1, 0.1),
+        Tile::Portal(_) => Color::srgb(0.55, 0.2, 0.8),
+    };
+
+    Some(Sprite::from_color(color, Vec2::splat(TILE_SIZE - 4.)))
+}
+
+fn draw_special_tiles(mut commands: Commands, board: Res<Board>) {
+    for x in 0..GRID_WIDTH {
+        for y in 0..GRID_HEIGHT {
+            let Some(sprite) = tile_sprite(&board.tiles[x][y]) else {
+                continue;
+            };
+            commands.spawn((
+                sprite,
+                Transform::from_translation(
+                    position_to_transform(&GridPosition { x, y }).extend(0.1),
+                ),
+                GridTile,
             ));
         }
     }
@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+
+use crate::{
+    board::Board,
+    following::Trailing,
+    grid::position_to_transform,
+    loading::TextureAssets,
+    player::{ActivePositions, GridPosition, SnakePart},
+    GameState,
+};
+
+pub struct HintsPlugin;
+
+/// This plugin highlights cells along the snake where the passing tail would switch gems into a match
+/// Hints are off by default and can be toggled with `H`
+impl Plugin for HintsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShowHints>()
+            .add_systems(
+                Update,
+                (
+                    toggle_hints,
+                    mark_hints.run_if(
+                        resource_changed::<ShowHints>
+                            .or(resource_changed::<ActivePositions>)
+                            .or(resource_changed::<Board>),
+                    ),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), remove_hints);
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ShowHints(pub bool);
+
+#[derive(Component)]
+struct HintMarker;
+
+fn toggle_hints(input: Res<ButtonInput<KeyCode>>, mut show_hints: ResMut<ShowHints>) {
+    if input.just_pressed(KeyCode::KeyH) {
+        show_hints.0 = !show_hints.0;
+    }
+}
+
+fn mark_hints(
+    mut commands: Commands,
+    show_hints: Res<ShowHints>,
+    board: Res<Board>,
+    markers: Query<Entity, With<HintMarker>>,
+    snake: Query<(&GridPosition, &Trailing), With<SnakePart>>,
+    positions: Query<&GridPosition, With<SnakePart>>,
+    asset: Res<TextureAssets>,
+) {
+    markers
+        .iter()
+        .for_each(|entity| commands.entity(entity).despawn());
+    if !show_hints.0 {
+        return;
+    }
+
+    // The tail passes every cell of the snake and leaves each one towards the part in front of it
+    for (position, trailing) in snake {
+        let Ok(next) = positions.get(trailing.0) else {
+            continue;
+        };
+        let Some(target) = board.find_tail_swap(position, next) else {
+            continue;
+        };
+        for cell in [position, &target] {
+            commands.spawn((
+                HintMarker,
+                Transform::from_translation(position_to_transform(cell).extend(0.5)),
+                Sprite {
+                    image: asset.active.clone(),
+                    color: Color::srgba(1., 1., 1., 0.5),
+                    ..default()
+                },
+            ));
+        }
+    }
+}
+
+fn remove_hints(mut commands: Commands, markers: Query<Entity, With<HintMarker>>) {
+    for marker in markers {
+        commands.entity(marker).despawn();
+    }
+}
@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+use bevy_rand::{global::GlobalEntropy, prelude::ChaCha8Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::{MoveDirection, Orientation},
+    board::Tile,
+    gems::{GemColors, GemType},
+    generator::GeneratorKind,
+    gravity::{Gravity, GravityShift},
+    grid::{Walls, GRID_HEIGHT, GRID_WIDTH},
+    player::GridPosition,
+};
+
+/// Path of the level file written and read by the editor
+#[cfg(not(target_family = "wasm"))]
+const LEVEL_FILE: &str = "assets/levels/custom.ron";
+
+pub const SNAKE_START_LENGTH: usize = 4;
+
+/// A hand-authored board and snake start
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Level {
+    pub gems: [[GemType; GRID_HEIGHT]; GRID_WIDTH],
+    #[serde(default)]
+    pub tiles: [[Tile; GRID_HEIGHT]; GRID_WIDTH],
+    pub snake: SnakeStart,
+    #[serde(default)]
+    pub gravity: Gravity,
+    #[serde(default)]
+    pub gravity_shifts: Vec<GravityShift>,
+    #[serde(default)]
+    pub generator: GeneratorKind,
+    #[serde(default)]
+    pub colors: GemColors,
+    /// Id of the visual theme while this level is played, instead of the one from the settings
+    #[serde(default)]
+    pub theme: Option<String>,
+}
+
+/// Start of the snake from its tail at `position`
+///
+/// Each part takes its entry in `moves` to get to the next part, the last entry belongs to the head.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SnakeStart {
+    pub position: GridPosition,
+    pub orientation: Orientation,
+    pub moves: Vec<MoveDirection>,
+}
+
+impl Default for SnakeStart {
+    fn default() -> Self {
+        SnakeStart {
+            position: GridPosition {
+                x: GRID_WIDTH / 2,
+                y: GRID_HEIGHT / 2,
+            },
+            orientation: Orientation::Up,
+            moves: vec![MoveDirection::Straight; SNAKE_START_LENGTH],
+        }
+    }
+}
+
+impl Level {
+    pub fn random(rng: &mut GlobalEntropy<ChaCha8Rng>) -> Self {
+        let colors = GemColors::default();
+        Level {
+            gems: std::array::from_fn(|_| std::array::from_fn(|_| colors.random(rng))),
+            tiles: default(),
+            snake: SnakeStart::default(),
+            gravity: Gravity::Down,
+            gravity_shifts: vec![],
+            generator: GeneratorKind::Random,
+            colors,
+            theme: None,
+        }
+    }
+
+    /// With `walls`, the snake may not reach across the border
+    #[cfg(not(target_family = "wasm"))]
+    pub fn load(walls: &Walls) -> Result<Self> {
+        let level: Level = ron::from_str(&std::fs::read_to_string(LEVEL_FILE)?)?;
+        if level.snake.moves.len() != SNAKE_START_LENGTH {
+            return Err(BevyError::from(format!(
+                "Level snake needs {SNAKE_START_LENGTH} parts, found {}",
+                level.snake.moves.len()
+            )));
+        }
+        let placements = crate::grid::placement(
+            &level.snake.position,
+            level.snake.orientation,
+            &level.snake.moves,
+        );
+        // The first placement is the tail at the start position
+        if let Some((_, _, _, position)) = placements
+            .iter()
+            .find(|(_, _, _, position)| position.x >= GRID_WIDTH || position.y >= GRID_HEIGHT)
+        {
+            return Err(BevyError::from(format!(
+                "Level snake is off the {GRID_WIDTH}x{GRID_HEIGHT} board at {position:?}"
+            )));
+        }
+        if walls.0 && crate::grid::crosses_border(&placements) {
+            return Err(BevyError::from(
+                "Level snake crosses the border, but walls are on",
+            ));
+        }
+
+        Ok(level)
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn save(&self) -> Result {
+        let path = std::path::Path::new(LEVEL_FILE);
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        std::fs::write(
+            path,
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?,
+        )?;
+
+        Ok(())
+    }
+
+    #[cfg(target_family = "wasm")]
+    pub fn load(_walls: &Walls) -> Result<Self> {
+        Err(BevyError::from("Levels can not be loaded in the browser"))
+    }
+
+    #[cfg(target_family = "wasm")]
+    pub fn save(&self) -> Result {
+        Err(BevyError::from("Levels can not be saved in the browser"))
+    }
+}
+
+/// If present, a new game starts from this level instead of a random board
+#[derive(Resource, Clone)]
+pub struct StartingLevel(pub Level);
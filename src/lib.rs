@@ -1,15 +1,38 @@
 #![allow(clippy::type_complexity)]
 
+mod accessibility;
 mod actions;
 mod audio;
 mod board;
+mod camera;
+mod cascade;
+mod clock;
+mod collision;
+mod daily;
+#[cfg(feature = "dev")]
+mod debug;
+mod editor;
 mod following;
 mod gems;
+mod generator;
+mod ghost;
+mod gravity;
 mod grid;
+mod hints;
+mod level;
 mod loading;
+mod locale;
 mod menu;
+mod mode;
 mod movement;
+mod navigation;
 mod player;
+mod power_ups;
+mod save;
+mod skins;
+mod storage;
+mod theme;
+mod themes;
 mod ui;
 
 use crate::actions::ActionsPlugin;
@@ -18,6 +41,7 @@ use crate::menu::MenuPlugin;
 use crate::movement::MovementPlugin;
 use crate::player::PlayerPlugin;
 
+use accessibility::AccessibilityPlugin;
 use audio::InternalAudioPlugin;
 use bevy::app::App;
 #[cfg(debug_assertions)]
@@ -25,8 +49,25 @@ use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::prelude::*;
 use bevy_enhanced_input::EnhancedInputSystem;
 use board::BoardPlugin;
+use camera::CameraPlugin;
+use cascade::CascadePlugin;
+use clock::ClockPlugin;
+use collision::CollisionPlugin;
+use daily::DailyPlugin;
+use editor::EditorPlugin;
 use gems::GemsPlugin;
+use generator::GeneratorPlugin;
+use ghost::GhostPlugin;
+use gravity::GravityPlugin;
 use grid::GridPlugin;
+use hints::HintsPlugin;
+use locale::LocalePlugin;
+use mode::GameModePlugin;
+use navigation::NavigationPlugin;
+use power_ups::PowerUpsPlugin;
+use save::SavePlugin;
+use skins::SkinsPlugin;
+use themes::ThemesPlugin;
 use ui::GameUiPlugin;
 
 // This example game uses States to separate logic
@@ -40,6 +81,12 @@ enum GameState {
     Playing,
     Restarting,
     Menu,
+    // Hand-authoring a level
+    Editor,
+    // Choosing the game mode before playing
+    ModeSelect,
+    // Changing the options of the game
+    Settings,
 }
 
 #[derive(SubStates, Clone, PartialEq, Eq, Hash, Debug, Default)]
@@ -70,6 +117,26 @@ impl Plugin for GamePlugin {
                 GemsPlugin,
                 BoardPlugin,
                 GameUiPlugin,
+                HintsPlugin,
+                EditorPlugin,
+                GravityPlugin,
+                GeneratorPlugin,
+            ))
+            .add_plugins((
+                SavePlugin,
+                DailyPlugin,
+                GameModePlugin,
+                CameraPlugin,
+                LocalePlugin,
+                AccessibilityPlugin,
+                NavigationPlugin,
+                PowerUpsPlugin,
+                SkinsPlugin,
+                ThemesPlugin,
+                GhostPlugin,
+                CollisionPlugin,
+                CascadePlugin,
+                ClockPlugin,
             ));
 
         #[cfg(debug_assertions)]
@@ -79,6 +146,8 @@ impl Plugin for GamePlugin {
                 LogDiagnosticsPlugin::default(),
             ));
         }
+        #[cfg(feature = "dev")]
+        app.add_plugins(debug::DebugPlugin);
         app.configure_sets(
             Update,
             (
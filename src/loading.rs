@@ -1,4 +1,11 @@
-use crate::{gems::GemType, GameState};
+use crate::{
+    accessibility::Palette,
+    gems::GemType,
+    locale::Translations,
+    skins::Skins,
+    themes::{ActiveTheme, Themes},
+    GameState,
+};
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
 
@@ -13,15 +20,15 @@ impl Plugin for LoadingPlugin {
             LoadingState::new(GameState::Loading)
                 .continue_to_state(GameState::Menu)
                 .load_collection::<AudioAssets>()
-                .load_collection::<TextureAssets>(),
+                .load_collection::<TextureAssets>()
+                .load_collection::<LocaleAssets>()
+                .load_collection::<ThemeAssets>(),
         );
     }
 }
 
 #[derive(AssetCollection, Resource)]
 pub struct AudioAssets {
-    #[asset(path = "audio/background.ogg")]
-    pub background: Handle<AudioSource>,
     #[asset(path = "audio/lost.ogg")]
     pub lost: Handle<AudioSource>,
     #[asset(path = "audio/click.ogg")]
@@ -34,36 +41,39 @@ pub struct AudioAssets {
     pub nomnom: Handle<AudioSource>,
 }
 
+#[derive(AssetCollection, Resource)]
+pub struct LocaleAssets {
+    #[asset(path = "locales/en.ftl")]
+    pub en: Handle<Translations>,
+    #[asset(path = "locales/de.ftl")]
+    pub de: Handle<Translations>,
+    #[asset(path = "locales/ru.ftl")]
+    pub ru: Handle<Translations>,
+    /// Covers the Latin and Cyrillic scripts of the translations
+    #[asset(path = "fonts/NotoSans-Regular.ttf")]
+    pub font: Handle<Font>,
+}
+
+/// Everything the visual themes need, see [`Themes`]
+#[derive(AssetCollection, Resource)]
+pub struct ThemeAssets {
+    #[asset(path = "themes/all.themes.ron")]
+    pub themes: Handle<Themes>,
+}
+
 #[derive(AssetCollection, Resource)]
 pub struct TextureAssets {
     #[asset(path = "textures/bevy.png")]
     pub bevy: Handle<Image>,
     #[asset(path = "textures/github.png")]
     pub github: Handle<Image>,
-    #[asset(path = "textures/head.png")]
-    pub head: Handle<Image>,
+    /// Shared by the atlases of every [`Skins`] entry
     #[asset(texture_atlas_layout(tile_size_x = 64, tile_size_y = 64, columns = 9, rows = 2))]
-    pub head_layout: Handle<TextureAtlasLayout>,
-    #[asset(path = "textures/head2.png")]
-    pub head2: Handle<Image>,
-    #[asset(texture_atlas_layout(tile_size_x = 64, tile_size_y = 64, columns = 9, rows = 2))]
-    pub head2_layout: Handle<TextureAtlasLayout>,
-    #[asset(path = "textures/tail.png")]
-    pub tail: Handle<Image>,
-    #[asset(texture_atlas_layout(tile_size_x = 64, tile_size_y = 64, columns = 9, rows = 2))]
-    pub tail_layout: Handle<TextureAtlasLayout>,
-    #[asset(path = "textures/tail2.png")]
-    pub tail2: Handle<Image>,
-    #[asset(texture_atlas_layout(tile_size_x = 64, tile_size_y = 64, columns = 9, rows = 2))]
-    pub tail2_layout: Handle<TextureAtlasLayout>,
-    #[asset(path = "textures/body.png")]
-    pub body: Handle<Image>,
-    #[asset(texture_atlas_layout(tile_size_x = 64, tile_size_y = 64, columns = 9, rows = 2))]
-    pub body_layout: Handle<TextureAtlasLayout>,
-    #[asset(path = "textures/tile.png")]
-    pub tile: Handle<Image>,
+    pub snake_layout: Handle<TextureAtlasLayout>,
+    #[asset(path = "skins/all.skins.ron")]
+    pub skins: Handle<Skins>,
     #[asset(path = "textures/active.png")]
-    pub _active: Handle<Image>,
+    pub active: Handle<Image>,
     #[asset(path = "textures/collision.png")]
     pub _collision: Handle<Image>,
     #[asset(path = "textures/gem1.png")]
@@ -76,16 +86,86 @@ pub struct TextureAssets {
     pub gem4: Handle<Image>,
     #[asset(path = "textures/gem5.png")]
     pub gem5: Handle<Image>,
+    #[asset(path = "textures/gem6.png")]
+    pub gem6: Handle<Image>,
+    #[asset(path = "textures/gem7.png")]
+    pub gem7: Handle<Image>,
+    #[asset(
+        paths(
+            "textures/protanopia/gem1.png",
+            "textures/protanopia/gem2.png",
+            "textures/protanopia/gem3.png",
+            "textures/protanopia/gem4.png",
+            "textures/protanopia/gem5.png",
+            "textures/protanopia/gem6.png",
+            "textures/protanopia/gem7.png"
+        ),
+        collection(typed)
+    )]
+    pub protanopia_gems: Vec<Handle<Image>>,
+    #[asset(
+        paths(
+            "textures/deuteranopia/gem1.png",
+            "textures/deuteranopia/gem2.png",
+            "textures/deuteranopia/gem3.png",
+            "textures/deuteranopia/gem4.png",
+            "textures/deuteranopia/gem5.png",
+            "textures/deuteranopia/gem6.png",
+            "textures/deuteranopia/gem7.png"
+        ),
+        collection(typed)
+    )]
+    pub deuteranopia_gems: Vec<Handle<Image>>,
+    #[asset(
+        paths(
+            "textures/tritanopia/gem1.png",
+            "textures/tritanopia/gem2.png",
+            "textures/tritanopia/gem3.png",
+            "textures/tritanopia/gem4.png",
+            "textures/tritanopia/gem5.png",
+            "textures/tritanopia/gem6.png",
+            "textures/tritanopia/gem7.png"
+        ),
+        collection(typed)
+    )]
+    pub tritanopia_gems: Vec<Handle<Image>>,
+    /// One symbol per gem type, in the order of [`GemType::ALL`]
+    #[asset(path = "textures/symbols.png")]
+    pub symbols: Handle<Image>,
+    #[asset(texture_atlas_layout(tile_size_x = 64, tile_size_y = 64, columns = 7, rows = 1))]
+    pub symbols_layout: Handle<TextureAtlasLayout>,
+    /// One icon per power-up, in the order of [`crate::power_ups::PowerUp::ALL`]
+    #[asset(path = "textures/power_ups.png")]
+    pub power_ups: Handle<Image>,
+    #[asset(texture_atlas_layout(tile_size_x = 64, tile_size_y = 64, columns = 4, rows = 1))]
+    pub power_ups_layout: Handle<TextureAtlasLayout>,
 }
 
 impl TextureAssets {
-    pub fn gem(&self, gem: &GemType) -> Handle<Image> {
+    /// The colour-blind palettes look the same in every theme
+    pub fn gem(&self, gem: &GemType, palette: Palette, theme: &ActiveTheme) -> Handle<Image> {
+        let gems = match palette {
+            Palette::Standard => {
+                return theme
+                    .gem(gem.index())
+                    .unwrap_or_else(|| self.standard_gem(gem))
+            }
+            Palette::Protanopia => &self.protanopia_gems,
+            Palette::Deuteranopia => &self.deuteranopia_gems,
+            Palette::Tritanopia => &self.tritanopia_gems,
+        };
+        gems[gem.index()].clone()
+    }
+
+    fn standard_gem(&self, gem: &GemType) -> Handle<Image> {
         match *gem {
             GemType::One => self.gem1.clone(),
             GemType::Two => self.gem2.clone(),
             GemType::Three => self.gem3.clone(),
             GemType::Four => self.gem4.clone(),
             GemType::Five => self.gem5.clone(),
+            GemType::Six => self.gem6.clone(),
+            GemType::Seven => self.gem7.clone(),
         }
     }
 }
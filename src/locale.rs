@@ -0,0 +1,216 @@
+use std::io;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    platform::collections::HashMap,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{loading::LocaleAssets, storage, GameState};
+
+/// Storage key of the selected [`Language`]
+const LANGUAGE_KEY: &str = "language.ron";
+
+pub struct LocalePlugin;
+
+/// This plugin translates every text with a [`Localized`] key into the selected language
+/// Translations are Fluent-like `.ftl` files in `assets/locales`, one per language
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Translations>()
+            .register_asset_loader(TranslationsLoader)
+            .insert_resource(Language::load())
+            .add_systems(OnExit(GameState::Loading), use_locale_font)
+            .add_systems(
+                Update,
+                (
+                    save_language
+                        .run_if(resource_changed::<Language>.and(not(resource_added::<Language>))),
+                    localize.run_if(resource_exists::<LocaleAssets>),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    Russian,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::English, Language::German, Language::Russian];
+
+    fn load() -> Self {
+        storage::read(LANGUAGE_KEY)
+            .and_then(|language| Ok(ron::from_str(&language)?))
+            .unwrap_or_default()
+    }
+
+    /// The language after this one in the settings
+    pub fn next(self) -> Self {
+        let index = Language::ALL
+            .iter()
+            .position(|language| *language == self)
+            .unwrap_or_default();
+        Language::ALL[(index + 1) % Language::ALL.len()]
+    }
+
+    fn translations(self, assets: &LocaleAssets) -> &Handle<Translations> {
+        match self {
+            Language::English => &assets.en,
+            Language::German => &assets.de,
+            Language::Russian => &assets.ru,
+        }
+    }
+}
+
+fn save_language(language: Res<Language>) {
+    let saved = ron::to_string(&*language)
+        .map_err(BevyError::from)
+        .and_then(|language| storage::write(LANGUAGE_KEY, &language));
+    if let Err(error) = saved {
+        warn!("Failed to save language: {error}");
+    }
+}
+
+/// Messages of one language by their key
+#[derive(Asset, TypePath, Default)]
+pub struct Translations(HashMap<String, String>);
+
+impl Translations {
+    /// Supports the subset of Fluent used by the game: `key = value` messages with `{ $variable }`
+    /// placeables, `#` comments and indented continuation lines
+    fn parse(source: &str) -> Self {
+        let mut messages: HashMap<String, String> = HashMap::new();
+        let mut last_key: Option<String> = None;
+        for line in source.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            if line.starts_with(char::is_whitespace) {
+                if let Some(value) = last_key.as_ref().and_then(|key| messages.get_mut(key)) {
+                    value.push('\n');
+                    value.push_str(trimmed);
+                    continue;
+                }
+            }
+            match trimmed.split_once('=') {
+                Some((key, value)) => {
+                    let key = key.trim().to_owned();
+                    messages.insert(key.clone(), value.trim().to_owned());
+                    last_key = Some(key);
+                }
+                None => warn!("Ignoring invalid translation line '{trimmed}'"),
+            }
+        }
+
+        Translations(messages)
+    }
+
+    fn format(&self, text: &Localized) -> Option<String> {
+        let mut message = self.0.get(text.key)?.as_str();
+        let mut formatted = String::with_capacity(message.len());
+        while let Some(start) = message.find('{') {
+            formatted.push_str(&message[..start]);
+            let Some(end) = message[start..].find('}') else {
+                break;
+            };
+            let placeable = message[start + 1..start + end].trim();
+            match placeable
+                .strip_prefix('$')
+                .and_then(|name| text.args.iter().find(|(arg, _)| *arg == name))
+            {
+                Some((_, value)) => formatted.push_str(value),
+                None => formatted.push_str(&message[start..=start + end]),
+            }
+            message = &message[start + end + 1..];
+        }
+        formatted.push_str(message);
+
+        Some(formatted)
+    }
+}
+
+#[derive(Default, TypePath)]
+struct TranslationsLoader;
+
+impl AssetLoader for TranslationsLoader {
+    type Asset = Translations;
+    type Settings = ();
+    type Error = io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Translations, io::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let source = std::str::from_utf8(&bytes)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        Ok(Translations::parse(source))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ftl"]
+    }
+}
+
+/// Text that is looked up in the translations and rendered again whenever the language changes
+///
+/// Update the text by replacing this component instead of writing into [`Text`].
+#[derive(Component, Clone, PartialEq, Debug)]
+#[require(Text)]
+pub struct Localized {
+    key: &'static str,
+    args: Vec<(&'static str, String)>,
+}
+
+impl Localized {
+    pub fn new(key: &'static str) -> Self {
+        Localized { key, args: vec![] }
+    }
+
+    /// Fill the placeable `{ $name }` of the message with `value`
+    pub fn with(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.args.push((name, value.to_string()));
+        self
+    }
+}
+
+fn localize(
+    language: Res<Language>,
+    assets: Res<LocaleAssets>,
+    translations: Res<Assets<Translations>>,
+    mut texts: Query<(Ref<Localized>, &mut Text)>,
+) {
+    let current = translations.get(language.translations(&assets));
+    // Messages missing in a translation fall back to English
+    let fallback = translations.get(&assets.en);
+    for (localized, mut text) in &mut texts {
+        if !language.is_changed() && !localized.is_changed() {
+            continue;
+        }
+        let translated = current
+            .and_then(|translations| translations.format(&localized))
+            .or_else(|| fallback.and_then(|translations| translations.format(&localized)))
+            .unwrap_or_else(|| localized.key.to_owned());
+        text.set_if_neq(Text(translated));
+    }
+}
+
+/// The font bundled with Bevy only covers Latin, so the default font is replaced with one that
+/// can also render the other scripts of the translations
+fn use_locale_font(assets: Res<LocaleAssets>, mut fonts: ResMut<Assets<Font>>) {
+    match fonts.get(&assets.font).cloned() {
+        Some(font) => fonts.insert(&Handle::<Font>::default(), font),
+        None => warn!("The locale font is not loaded"),
+    }
+}
@@ -1,7 +1,24 @@
+use crate::accessibility::{Accessibility, Palette};
+use crate::actions::{turn_buffer_label, TurnBuffer, TurnBufferButton, TurnBufferText};
 use crate::audio::SoundEffect;
+use crate::collision::{rule_label, CollisionRules, RuleButton, RuleText};
+use crate::daily::{today, DailyButton, DailyChallenge, DailyRecord};
+use crate::generator::{difficulty_label, Difficulty, DifficultyButton, DifficultyText};
+use crate::ghost::{Ghosts, RaceButton};
+use crate::gravity::{gravity_label, GravityButton, GravitySetting, GravityText};
+use crate::grid::WallsSetting;
 use crate::loading::TextureAssets;
+use crate::locale::{Language, Localized};
+use crate::mode::{GameMode, HighScores};
+use crate::navigation::{Activate, Focused};
+use crate::save::SavedRun;
+use crate::skins::{skin_label, SkinButton, SkinText, SnakeSkin};
+use crate::theme::{
+    button, label, panel, text, ButtonColors, BODY_FONT_SIZE, BUTTON_FONT_SIZE, CORNER_RADIUS,
+    OVERLAY_BACKGROUND, SMALL_FONT_SIZE, SPACING, TITLE_FONT_SIZE,
+};
+use crate::themes::{theme_label, ActiveTheme, ThemeButton, ThemeText};
 use crate::{GamePhase, GameState};
-use bevy::color::palettes::tailwind::SLATE_200;
 use bevy::prelude::*;
 
 pub struct MenuPlugin;
@@ -10,10 +27,30 @@ pub struct MenuPlugin;
 /// The menu is only drawn during the State `GameState::Menu` and is removed when that state is exited
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Menu), (camera, setup_menu))
-            .add_systems(Update, click_play_button.run_if(in_state(GameState::Menu)))
-            .add_systems(Update, click_play_button.run_if(in_state(GamePhase::Lost)))
+        app.add_observer(activate_button)
+            .add_systems(OnEnter(GameState::Menu), setup_menu)
+            .add_systems(
+                Update,
+                quick_restart.run_if(
+                    in_state(GameState::Menu)
+                        .or(in_state(GamePhase::Lost))
+                        .or(in_state(GameState::ModeSelect))
+                        .or(in_state(GameState::Settings)),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    update_walls_text.run_if(resource_changed::<WallsSetting>),
+                    update_accessibility_text.run_if(resource_changed::<Accessibility>),
+                )
+                    .run_if(in_state(GameState::Settings)),
+            )
             .add_systems(OnExit(GameState::Menu), cleanup_menu)
+            .add_systems(OnEnter(GameState::Settings), setup_settings)
+            .add_systems(OnExit(GameState::Settings), cleanup_menu)
+            .add_systems(OnEnter(GameState::ModeSelect), setup_mode_select)
+            .add_systems(OnExit(GameState::ModeSelect), cleanup_menu)
             .add_systems(Update, start_pause.run_if(in_state(GamePhase::Playing)))
             .add_systems(Update, stop_pause.run_if(in_state(GamePhase::Pause)))
             .add_systems(OnEnter(GamePhase::Lost), setup_menu)
@@ -21,41 +58,44 @@ impl Plugin for MenuPlugin {
     }
 }
 
-fn start_pause(input: Res<ButtonInput<KeyCode>>, mut next_state: ResMut<NextState<GamePhase>>) {
-    if input.just_pressed(KeyCode::Space) {
-        next_state.set(GamePhase::Pause);
-    }
+/// Space on the keyboard, the north face button or start on a gamepad
+fn pause_pressed(input: &ButtonInput<KeyCode>, gamepads: &Query<&Gamepad>) -> bool {
+    input.just_pressed(KeyCode::Space)
+        || gamepads.iter().any(|gamepad| {
+            gamepad.just_pressed(GamepadButton::North) || gamepad.just_pressed(GamepadButton::Start)
+        })
 }
 
-fn stop_pause(input: Res<ButtonInput<KeyCode>>, mut next_state: ResMut<NextState<GamePhase>>) {
-    if input.just_pressed(KeyCode::Space) {
-        next_state.set(GamePhase::Playing);
+fn start_pause(
+    input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut next_state: ResMut<NextState<GamePhase>>,
+) {
+    if pause_pressed(&input, &gamepads) {
+        next_state.set(GamePhase::Pause);
     }
 }
 
-#[derive(Component)]
-struct ButtonColors {
-    normal: Color,
-    hovered: Color,
-}
-
-impl Default for ButtonColors {
-    fn default() -> Self {
-        ButtonColors {
-            normal: Color::linear_rgba(0.15, 0.15, 0.15, 0.5),
-            hovered: Color::linear_rgb(0.25, 0.25, 0.25),
-        }
+fn stop_pause(
+    input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut next_state: ResMut<NextState<GamePhase>>,
+) {
+    if pause_pressed(&input, &gamepads) {
+        next_state.set(GamePhase::Playing);
     }
 }
 
 #[derive(Component)]
 struct Menu;
 
-fn camera(mut commands: Commands) {
-    commands.spawn((Camera2d, Msaa::Off));
-}
-
-fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>, state: Res<State<GameState>>) {
+pub fn setup_menu(
+    mut commands: Commands,
+    textures: Res<TextureAssets>,
+    state: Res<State<GameState>>,
+    daily_record: Res<DailyRecord>,
+    daily: Option<Res<DailyChallenge>>,
+) {
     info!("menu");
     let mut background = commands.spawn((
         Node {
@@ -69,41 +109,63 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>, state: Res<S
         Menu,
     ));
     if state.get() == &GameState::Playing {
-        background.insert(BackgroundColor(Color::Srgba(SLATE_200.with_alpha(0.2))));
+        background.insert(BackgroundColor(OVERLAY_BACKGROUND));
     }
+    let can_continue = state.get() == &GameState::Menu && SavedRun::load().is_ok();
     background.with_children(|children| {
-        let button_colors = ButtonColors::default();
-        let mut button = children.spawn((
-            Button,
-            Node {
-                width: Val::Px(250.0),
-                height: Val::Px(80.0),
-                justify_content: JustifyContent::Center,
-                align_items: AlignItems::Center,
-                ..Default::default()
-            },
-            BackgroundColor(button_colors.normal),
-            BorderRadius::all(Val::Px(10.)),
-            button_colors,
-        ));
+        if can_continue {
+            children
+                .spawn((button(250.0, 50.0), ContinueRun))
+                .with_child(label(Localized::new("continue"), BUTTON_FONT_SIZE));
+        }
 
+        let mut play = children.spawn(button(250.0, 80.0));
         if state.get() == &GameState::Playing {
-            button.insert(ChangeState(GameState::Restarting));
+            play.insert(ChangeState(GameState::Restarting));
         } else {
-            button.insert(ChangeState(GameState::Playing));
+            play.insert(ChangeState(GameState::ModeSelect));
         }
-        button.with_child((
-            Text::new(if state.get() == &GameState::Menu {
-                "Play"
+        play.with_child(label(
+            Localized::new(if state.get() == &GameState::Menu {
+                "play"
             } else {
-                "Try again"
+                "try-again"
             }),
-            TextFont {
-                font_size: 40.0,
-                ..default()
-            },
-            TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+            TITLE_FONT_SIZE,
         ));
+
+        if let Some(result) = daily.and_then(|daily| daily_record.result(daily.0)) {
+            children.spawn(panel()).with_child((
+                text(result, BODY_FONT_SIZE),
+                TextLayout::new_with_justify(JustifyText::Center),
+            ));
+            children
+                .spawn((button(250.0, 50.0), DailyButton::Share))
+                .with_child(label(Localized::new("copy-result"), BUTTON_FONT_SIZE));
+        }
+
+        if state.get() == &GameState::Menu {
+            let day = today();
+            children
+                .spawn((button(250.0, 50.0), DailyButton::Play))
+                .with_child(label(
+                    Localized::new(if daily_record.result(day).is_some() {
+                        "daily-share"
+                    } else if daily_record.played(day) {
+                        "daily-played"
+                    } else {
+                        "daily"
+                    })
+                    .with("streak", daily_record.streak(day)),
+                    BUTTON_FONT_SIZE,
+                ));
+            children
+                .spawn((button(250.0, 50.0), ChangeState(GameState::Editor)))
+                .with_child(label(Localized::new("editor"), BUTTON_FONT_SIZE));
+            children
+                .spawn((button(250.0, 50.0), ChangeState(GameState::Settings)))
+                .with_child(label(Localized::new("settings"), BUTTON_FONT_SIZE));
+        }
     });
     commands
         .spawn((
@@ -131,7 +193,7 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>, state: Res<S
                         padding: UiRect::all(Val::Px(5.)),
                         ..Default::default()
                     },
-                    BorderRadius::all(Val::Px(10.)),
+                    BorderRadius::all(Val::Px(CORNER_RADIUS)),
                     BackgroundColor(Color::NONE),
                     ButtonColors {
                         normal: Color::NONE,
@@ -140,14 +202,7 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>, state: Res<S
                     OpenLink("https://bevyengine.org"),
                 ))
                 .with_children(|parent| {
-                    parent.spawn((
-                        Text::new("Made with Bevy"),
-                        TextFont {
-                            font_size: 15.0,
-                            ..default()
-                        },
-                        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
-                    ));
+                    parent.spawn(label(Localized::new("made-with-bevy"), SMALL_FONT_SIZE));
                     parent.spawn((
                         ImageNode {
                             image: textures.bevy.clone(),
@@ -170,23 +225,16 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>, state: Res<S
                         padding: UiRect::all(Val::Px(5.)),
                         ..default()
                     },
-                    BorderRadius::all(Val::Px(10.)),
+                    BorderRadius::all(Val::Px(CORNER_RADIUS)),
                     BackgroundColor(Color::NONE),
                     ButtonColors {
                         normal: Color::NONE,
-                        hovered: Color::linear_rgb(0.25, 0.25, 0.25),
+                        ..default()
                     },
                     OpenLink("https://github.com/NiklasEi/bevy_jam_6"),
                 ))
                 .with_children(|parent| {
-                    parent.spawn((
-                        Text::new("Open source"),
-                        TextFont {
-                            font_size: 15.0,
-                            ..default()
-                        },
-                        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
-                    ));
+                    parent.spawn(label(Localized::new("open-source"), SMALL_FONT_SIZE));
                     parent.spawn((
                         ImageNode::new(textures.github.clone()),
                         Node {
@@ -204,44 +252,338 @@ struct ChangeState(GameState);
 #[derive(Component)]
 struct OpenLink(&'static str);
 
-fn click_play_button(
+#[derive(Component)]
+struct ToggleWalls;
+
+#[derive(Component)]
+struct ChangeLanguage;
+
+#[derive(Component)]
+struct CyclePalette;
+
+#[derive(Component)]
+struct ToggleSymbols;
+
+#[derive(Component)]
+struct CycleGameSpeed;
+
+#[derive(Component)]
+struct ToggleTurnBased;
+
+#[derive(Component)]
+struct ContinueRun;
+
+#[derive(Component)]
+struct SelectMode(GameMode);
+
+fn setup_mode_select(mut commands: Commands, high_scores: Res<HighScores>, ghosts: Res<Ghosts>) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            Menu,
+        ))
+        .with_children(|children| {
+            for mode in GameMode::ALL {
+                children
+                    .spawn((button(250.0, 60.0), SelectMode(mode)))
+                    .with_child(label(Localized::new(mode.key()), BUTTON_FONT_SIZE));
+                let table = high_scores.table(mode);
+                children.spawn((
+                    label(
+                        if table.is_empty() {
+                            Localized::new("no-high-scores")
+                        } else {
+                            Localized::new(mode.best_score_key()).with(
+                                "score",
+                                table
+                                    .iter()
+                                    .map(ToString::to_string)
+                                    .collect::<Vec<_>>()
+                                    .join(", "),
+                            )
+                        },
+                        SMALL_FONT_SIZE,
+                    ),
+                    Node {
+                        margin: UiRect::top(Val::Px(5.)),
+                        ..default()
+                    },
+                ));
+                if ghosts.contains(mode) {
+                    children
+                        .spawn((button(250.0, 40.0), RaceButton(mode)))
+                        .with_child(label(Localized::new("race-ghost"), BODY_FONT_SIZE));
+                }
+            }
+
+            children
+                .spawn((button(250.0, 50.0), ChangeState(GameState::Menu)))
+                .with_child(label(Localized::new("back"), BUTTON_FONT_SIZE));
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn setup_settings(
+    mut commands: Commands,
+    walls: Res<WallsSetting>,
+    accessibility: Res<Accessibility>,
+    skin: Res<SnakeSkin>,
+    theme: Res<ActiveTheme>,
+    rules: Res<CollisionRules>,
+    gravity: Res<GravitySetting>,
+    difficulty: Res<Difficulty>,
+    turn_buffer: Res<TurnBuffer>,
+) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            Menu,
+        ))
+        .with_children(|children| {
+            // The options take two columns where there is room for them
+            children
+                .spawn(Node {
+                    max_width: Val::Px(2. * 300.0 + SPACING),
+                    flex_wrap: FlexWrap::Wrap,
+                    justify_content: JustifyContent::Center,
+                    column_gap: Val::Px(SPACING),
+                    ..default()
+                })
+                .with_children(|options| {
+                    options
+                        .spawn((button(300.0, 50.0), ToggleWalls))
+                        .with_child((label(walls_label(&walls), BUTTON_FONT_SIZE), WallsText));
+                    options
+                        .spawn((button(300.0, 50.0), GravityButton))
+                        .with_child((
+                            label(gravity_label(&gravity), BUTTON_FONT_SIZE),
+                            GravityText,
+                        ));
+                    options
+                        .spawn((button(300.0, 50.0), DifficultyButton))
+                        .with_child((
+                            label(difficulty_label(&difficulty), BUTTON_FONT_SIZE),
+                            DifficultyText,
+                        ));
+                    options
+                        .spawn((button(300.0, 50.0), ChangeLanguage))
+                        .with_child(label(Localized::new("language"), BUTTON_FONT_SIZE));
+                    options
+                        .spawn((button(300.0, 50.0), SkinButton))
+                        .with_child((label(skin_label(&skin), BUTTON_FONT_SIZE), SkinText));
+                    options
+                        .spawn((button(300.0, 50.0), ThemeButton))
+                        .with_child((label(theme_label(&theme), BUTTON_FONT_SIZE), ThemeText));
+                    options
+                        .spawn((button(300.0, 50.0), CyclePalette))
+                        .with_child((
+                            label(palette_label(accessibility.palette), BUTTON_FONT_SIZE),
+                            PaletteText,
+                        ));
+                    options
+                        .spawn((button(300.0, 50.0), ToggleSymbols))
+                        .with_child((
+                            label(symbols_label(&accessibility), BUTTON_FONT_SIZE),
+                            SymbolsText,
+                        ));
+                    options
+                        .spawn((button(300.0, 50.0), CycleGameSpeed))
+                        .with_child((
+                            label(game_speed_label(&accessibility), BUTTON_FONT_SIZE),
+                            GameSpeedText,
+                        ));
+                    options
+                        .spawn((button(300.0, 50.0), ToggleTurnBased))
+                        .with_child((
+                            label(turn_based_label(&accessibility), BUTTON_FONT_SIZE),
+                            TurnBasedText,
+                        ));
+                    for setting in [TurnBufferButton::Size, TurnBufferButton::DropRedundant] {
+                        options.spawn((button(300.0, 50.0), setting)).with_child((
+                            label(turn_buffer_label(setting, &turn_buffer), BUTTON_FONT_SIZE),
+                            TurnBufferText(setting),
+                        ));
+                    }
+                    for rule in [
+                        RuleButton::TailSafe,
+                        RuleButton::Invulnerability,
+                        RuleButton::BiteOff,
+                    ] {
+                        options.spawn((button(300.0, 50.0), rule)).with_child((
+                            label(rule_label(rule, &rules), BUTTON_FONT_SIZE),
+                            RuleText(rule),
+                        ));
+                    }
+                });
+            children
+                .spawn((button(300.0, 50.0), ChangeState(GameState::Menu)))
+                .with_child(label(Localized::new("back"), BUTTON_FONT_SIZE));
+        });
+}
+
+#[derive(Component)]
+struct WallsText;
+
+#[derive(Component)]
+struct PaletteText;
+
+#[derive(Component)]
+struct SymbolsText;
+
+#[derive(Component)]
+struct GameSpeedText;
+
+#[derive(Component)]
+struct TurnBasedText;
+
+fn palette_label(palette: Palette) -> Localized {
+    Localized::new(palette.key())
+}
+
+fn symbols_label(accessibility: &Accessibility) -> Localized {
+    Localized::new(if accessibility.symbols {
+        "symbols-on"
+    } else {
+        "symbols-off"
+    })
+}
+
+fn game_speed_label(accessibility: &Accessibility) -> Localized {
+    Localized::new("game-speed").with("percent", accessibility.game_speed)
+}
+
+fn turn_based_label(accessibility: &Accessibility) -> Localized {
+    Localized::new(if accessibility.turn_based {
+        "turn-based-on"
+    } else {
+        "turn-based-off"
+    })
+}
+
+fn update_accessibility_text(
+    accessibility: Res<Accessibility>,
+    mut texts: Query<(
+        &mut Localized,
+        Has<PaletteText>,
+        Has<SymbolsText>,
+        Has<GameSpeedText>,
+        Has<TurnBasedText>,
+    )>,
+) {
+    for (mut text, palette, symbols, game_speed, turn_based) in &mut texts {
+        if palette {
+            text.set_if_neq(palette_label(accessibility.palette));
+        } else if symbols {
+            text.set_if_neq(symbols_label(&accessibility));
+        } else if game_speed {
+            text.set_if_neq(game_speed_label(&accessibility));
+        } else if turn_based {
+            text.set_if_neq(turn_based_label(&accessibility));
+        }
+    }
+}
+
+fn walls_label(walls: &WallsSetting) -> Localized {
+    Localized::new(if walls.0 { "walls-on" } else { "walls-off" })
+}
+
+fn update_walls_text(walls: Res<WallsSetting>, mut text: Query<&mut Localized, With<WallsText>>) {
+    for mut text in &mut text {
+        *text = walls_label(&walls);
+    }
+}
+
+/// Enter starts a new game right away, unless a button is focused
+fn quick_restart(
     input: Res<ButtonInput<KeyCode>>,
+    focused: Query<(), With<Focused>>,
     mut next_state: ResMut<NextState<GameState>>,
     mut writer: EventWriter<SoundEffect>,
-    mut interaction_query: Query<
-        (
-            &Interaction,
-            &mut BackgroundColor,
-            &ButtonColors,
-            Option<&ChangeState>,
-            Option<&OpenLink>,
-        ),
-        (Changed<Interaction>, With<Button>),
-    >,
 ) {
-    if input.just_pressed(KeyCode::Enter) {
+    if input.just_pressed(KeyCode::Enter) && focused.is_empty() {
         next_state.set(GameState::Restarting);
         writer.write(SoundEffect::Click);
-        return;
     }
-    for (interaction, mut color, button_colors, change_state, open_link) in &mut interaction_query {
-        match *interaction {
-            Interaction::Pressed => {
-                writer.write(SoundEffect::Click);
-                if let Some(state) = change_state {
-                    next_state.set(state.0.clone());
-                } else if let Some(link) = open_link {
-                    if let Err(error) = webbrowser::open(link.0) {
-                        warn!("Failed to open link {error:?}");
-                    }
-                }
-            }
-            Interaction::Hovered => {
-                *color = button_colors.hovered.into();
-            }
-            Interaction::None => {
-                *color = button_colors.normal.into();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn activate_button(
+    trigger: Trigger<Activate>,
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut walls: ResMut<WallsSetting>,
+    mut language: ResMut<Language>,
+    mut accessibility: ResMut<Accessibility>,
+    buttons: Query<(
+        Option<&ChangeState>,
+        Option<&OpenLink>,
+        Has<ToggleWalls>,
+        Has<ChangeLanguage>,
+        Has<CyclePalette>,
+        Has<ToggleSymbols>,
+        Has<CycleGameSpeed>,
+        Has<ToggleTurnBased>,
+        Has<ContinueRun>,
+        Option<&SelectMode>,
+    )>,
+) {
+    let Ok((
+        change_state,
+        open_link,
+        toggle_walls,
+        change_language,
+        cycle_palette,
+        toggle_symbols,
+        cycle_game_speed,
+        toggle_turn_based,
+        continue_run,
+        select_mode,
+    )) = buttons.get(trigger.target())
+    else {
+        return;
+    };
+    if continue_run {
+        match SavedRun::load() {
+            Ok(run) => {
+                run.resume(&mut commands);
+                next_state.set(GameState::Playing);
             }
+            Err(error) => warn!("Failed to continue run: {error}"),
+        }
+    } else if let Some(SelectMode(mode)) = select_mode {
+        mode.start(&mut commands);
+        next_state.set(GameState::Playing);
+    } else if let Some(state) = change_state {
+        next_state.set(state.0.clone());
+    } else if toggle_walls {
+        walls.0 = !walls.0;
+    } else if change_language {
+        *language = language.next();
+    } else if cycle_palette {
+        accessibility.palette = accessibility.palette.next();
+    } else if toggle_symbols {
+        accessibility.symbols = !accessibility.symbols;
+    } else if cycle_game_speed {
+        accessibility.game_speed = accessibility.next_game_speed();
+    } else if toggle_turn_based {
+        accessibility.turn_based = !accessibility.turn_based;
+    } else if let Some(link) = open_link {
+        if let Err(error) = webbrowser::open(link.0) {
+            warn!("Failed to open link {error:?}");
         }
     }
 }
@@ -0,0 +1,201 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audio::SoundEffect,
+    generator::GeneratorKind,
+    grid::Walls,
+    level::StartingLevel,
+    menu::setup_menu,
+    storage,
+    ui::{Explosions, SnakeLength},
+    GamePhase, GameState,
+};
+
+/// Storage key of the [`HighScores`]
+const HIGH_SCORES_KEY: &str = "high_scores.ron";
+
+const HIGH_SCORE_ENTRIES: usize = 5;
+
+pub const TIME_ATTACK_DURATION: Duration = Duration::from_secs(120);
+
+pub struct GameModePlugin;
+
+/// This plugin keeps track of the selected game mode, the time limit of time attack games and
+/// the high scores of every mode
+impl Plugin for GameModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameMode>()
+            .insert_resource(HighScores::load())
+            .insert_resource(TimeLimit(Timer::new(TIME_ATTACK_DURATION, TimerMode::Once)))
+            .add_systems(OnEnter(GameState::Playing), reset_time_limit)
+            .add_systems(
+                Update,
+                tick_time_limit
+                    .run_if(resource_equals(GameMode::TimeAttack))
+                    .run_if(
+                        in_state(GamePhase::Playing)
+                            .or(in_state(GamePhase::Exploding))
+                            .or(in_state(GamePhase::Waiting)),
+                    ),
+            )
+            .add_systems(OnEnter(GamePhase::Lost), record_score.before(setup_menu));
+    }
+}
+
+#[derive(
+    Resource, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum GameMode {
+    /// Survive as long as possible while the snake keeps growing
+    #[default]
+    Endless,
+    /// Destroy as many gems as possible before the time runs out
+    TimeAttack,
+    /// Matches never hurt the snake, only biting itself ends the game
+    Zen,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 3] = [GameMode::Endless, GameMode::TimeAttack, GameMode::Zen];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Endless => "Endless",
+            GameMode::TimeAttack => "Time Attack",
+            GameMode::Zen => "Zen",
+        }
+    }
+
+    /// Translation key of the name
+    pub fn key(&self) -> &'static str {
+        match self {
+            GameMode::Endless => "mode-endless",
+            GameMode::TimeAttack => "mode-time-attack",
+            GameMode::Zen => "mode-zen",
+        }
+    }
+
+    /// Translation key of the best score, which takes the `score` argument
+    pub fn best_score_key(&self) -> &'static str {
+        match self {
+            GameMode::Endless => "best-length",
+            GameMode::TimeAttack | GameMode::Zen => "best-gems",
+        }
+    }
+
+    /// What the high scores of this mode count
+    pub fn score_unit(&self) -> &'static str {
+        match self {
+            GameMode::Endless => "length",
+            GameMode::TimeAttack | GameMode::Zen => "gems",
+        }
+    }
+
+    pub fn score(&self, explosions: &Explosions, length: &SnakeLength) -> usize {
+        match self {
+            GameMode::Endless => length.0,
+            GameMode::TimeAttack | GameMode::Zen => explosions.0,
+        }
+    }
+
+    /// Refills of games in this mode that are not started from a level
+    ///
+    /// Endless refills never line up a match on their own, time attack keeps the free cascades that
+    /// add to the score and Zen deals the colours out evenly. Weighted refills need a factor for every
+    /// colour of a level and are left to levels.
+    pub fn generator(&self) -> GeneratorKind {
+        match self {
+            GameMode::Endless => GeneratorKind::NoInstantMatch,
+            GameMode::TimeAttack => GeneratorKind::Random,
+            GameMode::Zen => GeneratorKind::Bag,
+        }
+    }
+
+    /// Set up a new random game in this mode
+    ///
+    /// Has to happen before entering `GameState::Playing`.
+    pub fn start(self, commands: &mut Commands) {
+        info!("Starting {} game", self.name());
+        commands.remove_resource::<StartingLevel>();
+        if self == GameMode::Zen {
+            // Running into a wall would be the end of the game
+            commands.insert_resource(Walls(false));
+        }
+        commands.insert_resource(self);
+    }
+}
+
+/// Time left in a time attack game
+#[derive(Resource)]
+pub struct TimeLimit(pub Timer);
+
+pub fn reset_time_limit(mut time_limit: ResMut<TimeLimit>) {
+    time_limit.0.reset();
+}
+
+fn tick_time_limit(
+    time: Res<Time>,
+    mut time_limit: ResMut<TimeLimit>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut writer: EventWriter<SoundEffect>,
+) {
+    if time_limit.0.tick(time.delta()).just_finished() {
+        info!("Time is up");
+        next_phase.set(GamePhase::Lost);
+        writer.write(SoundEffect::Lost);
+    }
+}
+
+/// Best scores of each mode on this device, highest first
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct HighScores(BTreeMap<GameMode, Vec<usize>>);
+
+impl HighScores {
+    fn load() -> Self {
+        storage::read(HIGH_SCORES_KEY)
+            .and_then(|scores| Ok(ron::from_str(&scores)?))
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        let saved = ron::to_string(self)
+            .map_err(BevyError::from)
+            .and_then(|scores| storage::write(HIGH_SCORES_KEY, &scores));
+        if let Err(error) = saved {
+            warn!("Failed to save high scores: {error}");
+        }
+    }
+
+    pub fn table(&self, mode: GameMode) -> &[usize] {
+        self.0.get(&mode).map_or(&[], |scores| scores.as_slice())
+    }
+
+    pub fn best(&self, mode: GameMode) -> Option<usize> {
+        self.table(mode).first().copied()
+    }
+
+    fn insert(&mut self, mode: GameMode, score: usize) {
+        let scores = self.0.entry(mode).or_default();
+        let index = scores.partition_point(|entry| *entry >= score);
+        scores.insert(index, score);
+        scores.truncate(HIGH_SCORE_ENTRIES);
+    }
+}
+
+fn record_score(
+    mode: Res<GameMode>,
+    explosions: Res<Explosions>,
+    length: Res<SnakeLength>,
+    mut high_scores: ResMut<HighScores>,
+) {
+    let score = mode.score(&explosions, &length);
+    if score == 0 {
+        return;
+    }
+    info!("{} score: {score} {}", mode.name(), mode.score_unit());
+    high_scores.insert(*mode, score);
+    high_scores.save();
+}
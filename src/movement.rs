@@ -1,10 +1,14 @@
+use std::time::Duration;
+
 use bevy::{platform::collections::HashMap, prelude::*};
 
 use crate::{
-    actions::{MoveDirection, NextMove, Orientation},
+    actions::{MoveDirection, NextMove, Orientation, TurnQueue},
+    board::Board,
     following::Trailing,
-    grid::{wrap_translate, TILE_SIZE},
+    grid::{position_to_transform, Walls},
     player::{GridPosition, SnakePart, SnakeTail, StuckOnce},
+    power_ups::ActivePowerUps,
     AppSystems, GamePhase,
 };
 
@@ -26,10 +30,19 @@ impl Plugin for MovementPlugin {
 #[derive(Component)]
 pub struct MovementTimer(pub Timer);
 
+/// The snake ran into a wall with its next move
+#[derive(Component)]
+pub struct HitWall;
+
+#[allow(clippy::too_many_arguments)]
 fn player_movement(
     mut commands: Commands,
     time: Res<Time>,
+    power_ups: Res<ActivePowerUps>,
+    board: Res<Board>,
+    walls: Res<Walls>,
     tail: Query<Entity, With<SnakeTail>>,
+    mut turns: Query<&mut TurnQueue>,
     mut player_piece: Query<
         (
             Entity,
@@ -47,7 +60,9 @@ fn player_movement(
     >,
 ) -> Result {
     fn update_snake_piece(
-        time: &Time,
+        delta: Duration,
+        board: &Board,
+        walls: &Walls,
         commands: &mut Commands,
         piece: (
             Entity,
@@ -60,7 +75,7 @@ fn player_movement(
             &GridPosition,
             Option<&StuckOnce>,
         ),
-        new_move_direction: MoveDirection,
+        upcoming_move: impl FnOnce() -> MoveDirection,
     ) -> Result<bool> {
         let (
             entity,
@@ -73,7 +88,7 @@ fn player_movement(
             position,
             maybe_stuck,
         ) = piece;
-        timer.0.tick(time.delta());
+        timer.0.tick(delta);
         if timer.0.just_finished() {
             if let Some(atlas) = sprite.texture_atlas.as_mut() {
                 let mut row = atlas.index / ANIMATION_FRAMES;
@@ -84,11 +99,17 @@ fn player_movement(
                     }
                     *visibility = Visibility::Inherited;
                     orientation.next(next_move);
-                    let new_position = orientation.next_position(position);
+                    let Some(new_position) = board.next_position(orientation, position, walls)
+                    else {
+                        commands.entity(entity).insert(HitWall);
+                        return Ok(false);
+                    };
+                    // Set from the grid, since portals move pieces further than one tile
+                    transform.translation =
+                        position_to_transform(&new_position).extend(transform.translation.z);
                     commands.entity(entity).insert(new_position);
-                    transform.translation += orientation.direction() * TILE_SIZE;
-                    wrap_translate(&mut transform.translation);
                     transform.rotate_z(next_move.z_angle());
+                    let new_move_direction = upcoming_move();
                     next_move.0 = new_move_direction;
                     row = if new_move_direction == MoveDirection::Straight {
                         0
@@ -111,6 +132,7 @@ fn player_movement(
             directions.insert(entity, next_move.0);
         });
 
+    let delta = time.delta().mul_f32(power_ups.movement_speed());
     let mut next_entity = Some(tail.single()?);
 
     while let Some(entity) = next_entity {
@@ -126,17 +148,23 @@ fn player_movement(
             trailing,
             maybe_stuck,
         ) = player_piece.get_mut(entity)?;
-        let new_move_direction = if let Some(trailing) = trailing {
-            next_entity = Some(trailing.0);
+        let trailed_direction = trailing.map(|trailing| {
             *directions
                 .get(&trailing.0)
                 .expect("trailed entity has no next_move")
-        } else {
-            next_entity = None;
-            MoveDirection::Straight
+        });
+        next_entity = trailing.map(|trailing| trailing.0);
+        // Parts follow the part in front of them, the head takes the next buffered turn
+        let mut head_turns = turns.get_mut(entity).ok();
+        let upcoming_move = || {
+            trailed_direction
+                .or_else(|| head_turns.as_mut().map(|turns| turns.pop()))
+                .unwrap_or(MoveDirection::Straight)
         };
         if update_snake_piece(
-            &time,
+            delta,
+            &board,
+            &walls,
             &mut commands,
             (
                 entity,
@@ -149,7 +177,7 @@ fn player_movement(
                 grid_position,
                 maybe_stuck,
             ),
-            new_move_direction,
+            upcoming_move,
         )? {
             commands.entity(part).remove::<StuckOnce>();
         }
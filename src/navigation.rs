@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+
+use crate::{
+    audio::SoundEffect,
+    theme::{ButtonColors, FOCUS_OUTLINE},
+};
+
+/// How far the left stick has to be pushed to move the focus
+const STICK_THRESHOLD: f32 = 0.5;
+
+pub struct NavigationPlugin;
+
+/// This plugin makes every menu button usable without a mouse
+/// Arrow keys, the D-pad and the left stick move the focus between buttons, Enter or the south face
+/// button activate the focused one. Buttons react to [`Activate`] regardless of how they were pressed.
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(play_click)
+            .add_observer(show_focus)
+            .add_observer(hide_focus)
+            .add_systems(
+                Update,
+                (press_buttons, navigate, confirm)
+                    .chain()
+                    .run_if(any_with_component::<Button>),
+            );
+    }
+}
+
+/// Triggered on a button when it is clicked or confirmed while focused
+#[derive(Event)]
+pub struct Activate;
+
+/// The button that is activated when confirming
+#[derive(Component)]
+pub struct Focused;
+
+/// The focus was given by hovering the mouse and goes away once the mouse leaves
+#[derive(Component)]
+struct HoverFocus;
+
+fn play_click(_trigger: Trigger<Activate>, mut writer: EventWriter<SoundEffect>) {
+    writer.write(SoundEffect::Click);
+}
+
+fn show_focus(trigger: Trigger<OnAdd, Focused>, mut commands: Commands) {
+    commands
+        .entity(trigger.target())
+        .insert(Outline::new(Val::Px(3.), Val::Px(2.), FOCUS_OUTLINE));
+}
+
+fn hide_focus(trigger: Trigger<OnRemove, Focused>, mut commands: Commands) {
+    commands
+        .entity(trigger.target())
+        .try_remove::<(Outline, HoverFocus)>();
+}
+
+fn press_buttons(
+    mut commands: Commands,
+    mut buttons: Query<
+        (
+            Entity,
+            &Interaction,
+            &mut BackgroundColor,
+            &ButtonColors,
+            Has<HoverFocus>,
+        ),
+        (Changed<Interaction>, With<Button>),
+    >,
+    focused: Query<Entity, With<Focused>>,
+) {
+    for (entity, interaction, mut color, button_colors, hover_focus) in &mut buttons {
+        match *interaction {
+            Interaction::Pressed => {
+                commands.trigger_targets(Activate, entity);
+            }
+            Interaction::Hovered => {
+                *color = button_colors.hovered.into();
+                // Keyboard and mouse share the same focus
+                for previous in &focused {
+                    commands.entity(previous).remove::<Focused>();
+                }
+                commands.entity(entity).insert((Focused, HoverFocus));
+            }
+            Interaction::None => {
+                *color = button_colors.normal.into();
+                // Focus moved here with the keyboard or gamepad stays
+                if hover_focus {
+                    commands.entity(entity).remove::<Focused>();
+                }
+            }
+        }
+    }
+}
+
+/// Direction the player wants to move the focus in, with y pointing down like the UI
+fn navigation_input(
+    keys: &ButtonInput<KeyCode>,
+    gamepads: &Query<&Gamepad>,
+    stick_held: &mut bool,
+) -> Option<Vec2> {
+    let pressed = |key: KeyCode, button: GamepadButton| {
+        keys.just_pressed(key) || gamepads.iter().any(|gamepad| gamepad.just_pressed(button))
+    };
+    if pressed(KeyCode::ArrowUp, GamepadButton::DPadUp) {
+        return Some(Vec2::NEG_Y);
+    }
+    if pressed(KeyCode::ArrowDown, GamepadButton::DPadDown) {
+        return Some(Vec2::Y);
+    }
+    if pressed(KeyCode::ArrowLeft, GamepadButton::DPadLeft) {
+        return Some(Vec2::NEG_X);
+    }
+    if pressed(KeyCode::ArrowRight, GamepadButton::DPadRight) {
+        return Some(Vec2::X);
+    }
+
+    // The stick moves the focus once per push
+    let stick = gamepads
+        .iter()
+        .map(|gamepad| gamepad.left_stick())
+        .find(|stick| stick.length() >= STICK_THRESHOLD);
+    let was_held = *stick_held;
+    *stick_held = stick.is_some();
+    let stick = stick.filter(|_| !was_held)?;
+    Some(if stick.x.abs() > stick.y.abs() {
+        Vec2::new(stick.x.signum(), 0.)
+    } else {
+        Vec2::new(0., -stick.y.signum())
+    })
+}
+
+fn navigate(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    buttons: Query<(Entity, &GlobalTransform, &InheritedVisibility), With<Button>>,
+    focused: Query<(Entity, &GlobalTransform), With<Focused>>,
+    mut stick_held: Local<bool>,
+) {
+    let Some(direction) = navigation_input(&keys, &gamepads, &mut stick_held) else {
+        return;
+    };
+    let visible = buttons
+        .iter()
+        .filter(|(_, _, visibility)| visibility.get())
+        .map(|(entity, transform, _)| (entity, transform.translation().truncate()));
+
+    let next = match focused.single() {
+        Ok((current, transform)) => {
+            let from = transform.translation().truncate();
+            // The closest button in the direction, preferring ones that are in line with the current one
+            visible
+                .filter_map(|(entity, position)| {
+                    let offset = position - from;
+                    let along = offset.dot(direction);
+                    let across = (offset - direction * along).length();
+                    (entity != current && along > 1.).then_some((entity, along + 2. * across))
+                })
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(entity, _)| entity)
+        }
+        // Start at the top of the menu
+        Err(_) => visible
+            .min_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)))
+            .map(|(entity, _)| entity),
+    };
+    if let Some(next) = next {
+        for (previous, _) in &focused {
+            commands.entity(previous).remove::<Focused>();
+        }
+        commands.entity(next).insert(Focused);
+    }
+}
+
+fn confirm(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    focused: Query<Entity, With<Focused>>,
+) {
+    let confirmed = keys.just_pressed(KeyCode::Enter)
+        || gamepads
+            .iter()
+            .any(|gamepad| gamepad.just_pressed(GamepadButton::South));
+    if !confirmed {
+        return;
+    }
+    for entity in &focused {
+        commands.trigger_targets(Activate, entity);
+    }
+}
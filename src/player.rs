@@ -1,12 +1,17 @@
 use std::time::Duration;
 
-use crate::actions::{MoveDirection, NextMove, Orientation, Player};
+use crate::actions::{MoveDirection, NextMove, Orientation, Player, TurnQueue};
 use crate::audio::SoundEffect;
-use crate::board::fill_board;
+use crate::board::{fill_board, Board, Tile};
+use crate::collision::{CollisionRules, Invulnerability, SelfCollision};
 use crate::following::Trailing;
-use crate::grid::{position_to_transform, random_placement, GRID_HEIGHT, GRID_WIDTH};
-use crate::loading::TextureAssets;
-use crate::movement::MovementTimer;
+use crate::grid::{placement, random_placement, Walls, GRID_HEIGHT, GRID_WIDTH};
+use crate::level::{StartingLevel, SNAKE_START_LENGTH};
+use crate::mode::GameMode;
+use crate::movement::{HitWall, MovementTimer};
+use crate::power_ups::{ActivePowerUps, PendingShrink, PowerUp};
+use crate::save::ResumedRun;
+use crate::skins::SnakeSkin;
 use crate::ui::{Explosions, SnakeLength};
 use crate::{AppSystems, GamePhase, GameState};
 use bevy::platform::collections::HashSet;
@@ -14,6 +19,7 @@ use bevy::prelude::*;
 use bevy_enhanced_input::prelude::Actions;
 use bevy_rand::global::GlobalEntropy;
 use bevy_rand::prelude::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
 
 pub struct PlayerPlugin;
 
@@ -24,14 +30,21 @@ impl Plugin for PlayerPlugin {
         app.init_resource::<ActivePositions>()
             .init_resource::<SnakePositions>()
             .insert_resource(GrowthTimer(Timer::from_seconds(5., TimerMode::Repeating)))
-            .add_systems(OnEnter(GameState::Playing), spawn_player.before(fill_board))
+            .add_systems(
+                OnEnter(GameState::Playing),
+                spawn_player
+                    .before(fill_board)
+                    .run_if(not(resource_exists::<ResumedRun>)),
+            )
             .add_systems(
                 Update,
                 (
                     update_player_direction.in_set(AppSystems::Input),
                     (check_collisions, update_active)
                         .run_if(in_state(GamePhase::Playing))
-                        .run_if(resource_changed::<SnakePositions>)
+                        .run_if(
+                            resource_changed::<SnakePositions>.or(any_with_component::<HitWall>),
+                        )
                         .chain()
                         .in_set(AppSystems::CheckCollision),
                     grow_snake.in_set(AppSystems::Spawn),
@@ -45,6 +58,7 @@ impl Plugin for PlayerPlugin {
 }
 
 #[derive(Component)]
+#[require(TurnQueue)]
 pub struct SnakeHead;
 
 #[derive(Component)]
@@ -62,26 +76,35 @@ fn remove_player(mut commands: Commands, snake: Query<Entity, With<SnakePart>>)
     }
 }
 
-fn spawn_player(
+pub fn spawn_player(
     mut commands: Commands,
-    textures: Res<TextureAssets>,
+    skin: Res<SnakeSkin>,
     mut rng: GlobalEntropy<ChaCha8Rng>,
     mut length: ResMut<SnakeLength>,
+    level: Option<Res<StartingLevel>>,
+    walls: Res<Walls>,
 ) {
     commands.insert_resource(GrowthTimer(Timer::from_seconds(5., TimerMode::Repeating)));
     commands.insert_resource(SnakePositions::default());
     commands.insert_resource(Explosions::default());
-    let mut placements = random_placement(4, &mut rng);
-    length.0 = 4;
+    let mut placements = match level {
+        Some(level) => placement(
+            &level.0.snake.position,
+            level.0.snake.orientation,
+            &level.0.snake.moves,
+        ),
+        None => random_placement(SNAKE_START_LENGTH as u8, &walls, &mut rng),
+    };
+    length.0 = SNAKE_START_LENGTH;
     info!("Starting positions: {placements:?}");
     let mut placement = placements.pop().unwrap();
     let head = commands
         .spawn((
             Sprite::from_atlas_image(
-                textures.head.clone(),
+                skin.head.clone(),
                 TextureAtlas {
                     index: 0,
-                    layout: textures.head_layout.clone(),
+                    layout: skin.layout.clone(),
                 },
             ),
             placement.2,
@@ -98,10 +121,10 @@ fn spawn_player(
     let head2 = commands
         .spawn((
             Sprite::from_atlas_image(
-                textures.head2.clone(),
+                skin.head2.clone(),
                 TextureAtlas {
                     index: 0,
-                    layout: textures.head2_layout.clone(),
+                    layout: skin.layout.clone(),
                 },
             ),
             placement.2,
@@ -118,10 +141,10 @@ fn spawn_player(
     let tail2 = commands
         .spawn((
             Sprite::from_atlas_image(
-                textures.tail2.clone(),
+                skin.tail2.clone(),
                 TextureAtlas {
                     index: 0,
-                    layout: textures.tail2_layout.clone(),
+                    layout: skin.layout.clone(),
                 },
             ),
             placement.2,
@@ -137,10 +160,10 @@ fn spawn_player(
     placement = placements.pop().unwrap();
     commands.spawn((
         Sprite::from_atlas_image(
-            textures.tail.clone(),
+            skin.tail.clone(),
             TextureAtlas {
                 index: 0,
-                layout: textures.tail_layout.clone(),
+                layout: skin.layout.clone(),
             },
         ),
         placement.2,
@@ -181,7 +204,7 @@ pub struct StuckOnce;
 #[allow(clippy::too_many_arguments)]
 fn grow_snake(
     mut commands: Commands,
-    textures: Res<TextureAssets>,
+    skin: Res<SnakeSkin>,
     mut writer: EventWriter<SoundEffect>,
     inner_tail: Query<
         (
@@ -200,11 +223,14 @@ fn grow_snake(
     time: Res<Time>,
     mut timer: ResMut<GrowthTimer>,
     mut length: ResMut<SnakeLength>,
+    rules: Res<CollisionRules>,
+    mut invulnerability: ResMut<Invulnerability>,
 ) -> Result {
     timer.0.tick(time.delta());
     if timer.0.just_finished() {
         writer.write(SoundEffect::Grow);
         length.0 += 1;
+        invulnerability.0 = rules.invulnerable_steps;
         let (
             inner_tail,
             transform,
@@ -218,10 +244,10 @@ fn grow_snake(
         let new_body_part = commands
             .spawn((
                 Sprite::from_atlas_image(
-                    textures.body.clone(),
+                    skin.body.clone(),
                     TextureAtlas {
                         index: sprite.texture_atlas.as_ref().unwrap().index,
-                        layout: textures.body_layout.clone(),
+                        layout: skin.layout.clone(),
                     },
                 ),
                 *orientation,
@@ -251,10 +277,13 @@ fn grow_snake(
     Ok(())
 }
 
+/// Body parts by cell, the head and the tail are left out
+/// The head can't collide with itself and whether the tail's cell is safe is up to
+/// [`CollisionRules::tail_safe`]. Parts that were just spawned join after their first step.
 #[derive(Resource, Default, Debug)]
-struct SnakePositions([[Vec<Entity>; GRID_HEIGHT]; GRID_WIDTH]);
+pub struct SnakePositions(pub [[Vec<Entity>; GRID_HEIGHT]; GRID_WIDTH]);
 
-#[derive(Component, Clone, Debug, Hash, Eq, PartialEq, Default)]
+#[derive(Component, Clone, Debug, Hash, Eq, PartialEq, Default, Serialize, Deserialize)]
 #[component(immutable)]
 pub struct GridPosition {
     pub x: usize,
@@ -338,17 +367,71 @@ fn on_grid_position_replaced(
     }
 }
 
+/// Self collisions are only checked when the head takes a step, the [`CollisionRules`] decide
+/// what happens
+#[allow(clippy::too_many_arguments)]
 fn check_collisions(
     positions: Res<SnakePositions>,
-    head: Query<&GridPosition, With<SnakeHead>>,
+    board: Res<Board>,
+    head: Query<(Ref<GridPosition>, Has<HitWall>), With<SnakeHead>>,
+    tail: Query<Entity, With<SnakeTail>>,
+    parts: Query<(&GridPosition, Option<&Trailing>), With<SnakePart>>,
     mut next_phase: ResMut<NextState<GamePhase>>,
     mut writer: EventWriter<SoundEffect>,
+    mode: Res<GameMode>,
+    power_ups: Res<ActivePowerUps>,
+    rules: Res<CollisionRules>,
+    mut invulnerability: ResMut<Invulnerability>,
+    mut pending_shrink: ResMut<PendingShrink>,
 ) -> Result {
-    let head = head.single()?;
-    if !positions.0[head.x][head.y].is_empty() {
-        info!("Snake bit itself at {}/{}", head.x, head.y);
+    let (head, hit_wall) = head.single()?;
+    // A ghost still can't leave the board
+    let ghost = power_ups.is_active(PowerUp::Ghost);
+    if hit_wall {
+        info!("Snake ran into the wall at {}/{}", head.x, head.y);
+        next_phase.set(GamePhase::Lost);
+        writer.write(SoundEffect::Lost);
+        return Ok(());
+    }
+    if head.is_changed() {
+        let invulnerable = invulnerability.step();
+        let tail = tail.single()?;
+        let tail_bitten = parts.get(tail)?.0 == &*head;
+        if !ghost && (tail_bitten || !positions.0[head.x][head.y].is_empty()) {
+            let mut snake = vec![tail];
+            while let Some(Trailing(next)) = parts.get(snake[snake.len() - 1])?.1 {
+                snake.push(*next);
+            }
+            let bitten = snake[..snake.len() - 1]
+                .iter()
+                .enumerate()
+                .filter(|(_, part)| {
+                    parts
+                        .get(**part)
+                        .is_ok_and(|(position, _)| position == &*head)
+                })
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+            match rules.self_collision(&bitten, snake.len(), invulnerable) {
+                SelfCollision::Safe => (),
+                SelfCollision::BiteOff(cut) => {
+                    info!("Snake bit off {cut} parts at {}/{}", head.x, head.y);
+                    pending_shrink.0 += cut;
+                    writer.write(SoundEffect::NomNom);
+                }
+                SelfCollision::Dead => {
+                    info!("Snake bit itself at {}/{}", head.x, head.y);
+                    next_phase.set(GamePhase::Lost);
+                    writer.write(SoundEffect::NomNom);
+                    return Ok(());
+                }
+            }
+        }
+    }
+    if !ghost && *mode != GameMode::Zen && board.tiles[head.x][head.y] == Tile::Stone {
+        info!("Snake hit a stone at {}/{}", head.x, head.y);
         next_phase.set(GamePhase::Lost);
-        writer.write(SoundEffect::NomNom);
+        writer.write(SoundEffect::Lost);
     }
 
     Ok(())
@@ -366,39 +449,3 @@ fn update_active(
         active.0.push(pos.clone());
     }
 }
-
-#[derive(Component)]
-struct ActiveMarker;
-
-fn _mark_taken(
-    active: Query<Entity, With<ActiveMarker>>,
-    mut commands: Commands,
-    asset: Res<TextureAssets>,
-    positions: Res<SnakePositions>,
-) {
-    active
-        .iter()
-        .for_each(|entity| commands.entity(entity).despawn());
-
-    for x in 0..GRID_WIDTH {
-        for y in 0..GRID_HEIGHT {
-            if positions.0[x][y].len() == 1 {
-                commands.spawn((
-                    ActiveMarker,
-                    Transform::from_translation(
-                        position_to_transform(&GridPosition { x, y }).extend(0.),
-                    ),
-                    Sprite::from_image(asset._active.clone()),
-                ));
-            } else if positions.0[x][y].len() > 1 {
-                commands.spawn((
-                    ActiveMarker,
-                    Transform::from_translation(
-                        position_to_transform(&GridPosition { x, y }).extend(0.),
-                    ),
-                    Sprite::from_image(asset._collision.clone()),
-                ));
-            }
-        }
-    }
-}
@@ -0,0 +1,413 @@
+use std::time::Duration;
+
+use bevy::{
+    ecs::system::SystemParam,
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
+use bevy_rand::{global::GlobalEntropy, prelude::ChaCha8Rng};
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audio::SoundEffect,
+    board::{Board, Tile},
+    following::Trailing,
+    gems::draw_board,
+    level::SNAKE_START_LENGTH,
+    loading::TextureAssets,
+    player::{
+        ActivePositions, GridPosition, SnakeHead, SnakePositions, SnakeTail, SnakeTailInner,
+        StuckOnce,
+    },
+    skins::SnakeSkin,
+    ui::SnakeLength,
+    AppSystems, GamePhase, GameState,
+};
+
+const SPAWN_INTERVAL: Duration = Duration::from_secs(12);
+const MAX_POWER_UPS: usize = 2;
+/// Parts removed from the tail by [`PowerUp::Shrink`]
+const SHRINK_PARTS: usize = 3;
+/// Speed of the snake while [`PowerUp::SlowTime`] is active
+const SLOW_TIME_SPEED: f32 = 0.5;
+
+pub struct PowerUpsPlugin;
+
+/// This plugin places power-ups on gems from time to time
+/// The head picks them up by moving onto the gem. Their effects run out after a while.
+impl Plugin for PowerUpsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActivePowerUps>()
+            .init_resource::<PendingShrink>()
+            .insert_resource(PowerUpTimer(Timer::new(
+                SPAWN_INTERVAL,
+                TimerMode::Repeating,
+            )))
+            .add_observer(show_power_up)
+            .add_observer(hide_power_up)
+            .add_systems(OnEnter(GameState::Playing), reset_power_ups)
+            .add_systems(
+                OnEnter(GameState::Playing),
+                place_restored_pickups
+                    .after(draw_board)
+                    .run_if(resource_exists::<RestoredPickups>),
+            )
+            .add_systems(
+                Update,
+                (
+                    (spawn_power_ups, tick_power_ups),
+                    pick_up_power_ups.in_set(AppSystems::Spawn),
+                    shrink_snake
+                        .in_set(AppSystems::Spawn)
+                        .after(pick_up_power_ups)
+                        .run_if(|pending: Res<PendingShrink>| pending.0 > 0),
+                )
+                    .run_if(in_state(GamePhase::Playing)),
+            );
+    }
+}
+
+/// Lies on a gem until the head picks it up
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PowerUp {
+    /// The snake moves slower for a while
+    SlowTime,
+    /// The snake can pass through itself and stones for a while
+    Ghost,
+    /// The gems around the head explode
+    Detonate,
+    /// The snake loses a few parts of its tail
+    Shrink,
+}
+
+impl PowerUp {
+    pub const ALL: [PowerUp; 4] = [
+        PowerUp::SlowTime,
+        PowerUp::Ghost,
+        PowerUp::Detonate,
+        PowerUp::Shrink,
+    ];
+
+    /// How long the effect lasts, instant ones stay in the HUD for a moment
+    fn duration(&self) -> Duration {
+        match self {
+            PowerUp::SlowTime => Duration::from_secs(8),
+            PowerUp::Ghost => Duration::from_secs(5),
+            PowerUp::Detonate | PowerUp::Shrink => Duration::from_millis(1500),
+        }
+    }
+
+    /// Translation key of the name
+    pub fn key(&self) -> &'static str {
+        match self {
+            PowerUp::SlowTime => "power-up-slow-time",
+            PowerUp::Ghost => "power-up-ghost",
+            PowerUp::Detonate => "power-up-detonate",
+            PowerUp::Shrink => "power-up-shrink",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Remaining time of every power-up that was picked up
+#[derive(Resource, Default)]
+pub struct ActivePowerUps(HashMap<PowerUp, Timer>);
+
+impl ActivePowerUps {
+    pub fn is_active(&self, power_up: PowerUp) -> bool {
+        self.0.contains_key(&power_up)
+    }
+
+    /// Share of the duration that is left
+    pub fn remaining(&self, power_up: PowerUp) -> Option<f32> {
+        self.0.get(&power_up).map(Timer::fraction_remaining)
+    }
+
+    /// Factor for the movement timers of the snake
+    pub fn movement_speed(&self) -> f32 {
+        if self.is_active(PowerUp::SlowTime) {
+            SLOW_TIME_SPEED
+        } else {
+            1.
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct PowerUpTimer(Timer);
+
+/// Present until the gems around the position are marked for explosion
+#[derive(Resource)]
+pub struct Detonation(pub GridPosition);
+
+/// Tail parts still to remove, waits while the snake grows
+#[derive(Resource, Default)]
+pub struct PendingShrink(pub usize);
+
+#[derive(Component)]
+struct PowerUpIcon;
+
+/// Power-ups of a run as they are kept in a save
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SavedPowerUps {
+    /// Time every running effect has been active for
+    active: Vec<(PowerUp, Duration)>,
+    /// Power-ups lying on gems
+    pickups: Vec<(GridPosition, PowerUp)>,
+    pending_shrink: usize,
+    /// Time towards placing the next power-up
+    spawn: Duration,
+}
+
+impl SavedPowerUps {
+    /// Has to happen after [`reset_power_ups`], the pickups are placed once the gems are drawn
+    pub fn restore(&self, commands: &mut Commands) {
+        let active = self
+            .active
+            .iter()
+            .map(|(power_up, elapsed)| {
+                let mut timer = Timer::new(power_up.duration(), TimerMode::Once);
+                timer.tick(*elapsed);
+                (*power_up, timer)
+            })
+            .collect();
+        let mut timer = Timer::new(SPAWN_INTERVAL, TimerMode::Repeating);
+        timer.tick(self.spawn);
+        commands.insert_resource(ActivePowerUps(active));
+        commands.insert_resource(PowerUpTimer(timer));
+        commands.insert_resource(PendingShrink(self.pending_shrink));
+        commands.insert_resource(RestoredPickups(self.pickups.clone()));
+    }
+}
+
+/// Everything about the power-ups that a save records
+#[derive(SystemParam)]
+pub struct PowerUpState<'w, 's> {
+    active: Res<'w, ActivePowerUps>,
+    timer: Res<'w, PowerUpTimer>,
+    pending_shrink: Res<'w, PendingShrink>,
+    pickups: Query<'w, 's, (&'static GridPosition, &'static PowerUp)>,
+}
+
+impl PowerUpState<'_, '_> {
+    pub fn save(&self) -> SavedPowerUps {
+        SavedPowerUps {
+            active: self
+                .active
+                .0
+                .iter()
+                .map(|(power_up, timer)| (*power_up, timer.elapsed()))
+                .collect(),
+            pickups: self
+                .pickups
+                .iter()
+                .map(|(position, power_up)| (position.clone(), *power_up))
+                .collect(),
+            pending_shrink: self.pending_shrink.0,
+            spawn: self.timer.0.elapsed(),
+        }
+    }
+}
+
+/// Power-ups of a continued run that wait for their gems to be drawn
+#[derive(Resource)]
+struct RestoredPickups(Vec<(GridPosition, PowerUp)>);
+
+fn place_restored_pickups(
+    mut commands: Commands,
+    pickups: Res<RestoredPickups>,
+    board: Res<Board>,
+) {
+    commands.remove_resource::<RestoredPickups>();
+    for (position, power_up) in &pickups.0 {
+        if let Some(gem) = board.gems[position.x][position.y].entity {
+            commands.entity(gem).insert(*power_up);
+        }
+    }
+}
+
+pub fn reset_power_ups(
+    mut active: ResMut<ActivePowerUps>,
+    mut timer: ResMut<PowerUpTimer>,
+    mut pending_shrink: ResMut<PendingShrink>,
+) {
+    active.0.clear();
+    timer.0.reset();
+    pending_shrink.0 = 0;
+}
+
+fn show_power_up(
+    trigger: Trigger<OnAdd, PowerUp>,
+    mut commands: Commands,
+    power_ups: Query<&PowerUp>,
+    textures: Res<TextureAssets>,
+) {
+    let Ok(power_up) = power_ups.get(trigger.target()) else {
+        return;
+    };
+    commands.entity(trigger.target()).with_child((
+        Sprite::from_atlas_image(
+            textures.power_ups.clone(),
+            TextureAtlas {
+                layout: textures.power_ups_layout.clone(),
+                index: power_up.index(),
+            },
+        ),
+        Transform::from_xyz(0., 0., 0.3),
+        PowerUpIcon,
+    ));
+}
+
+fn hide_power_up(
+    trigger: Trigger<OnRemove, PowerUp>,
+    mut commands: Commands,
+    children: Query<&Children>,
+    icons: Query<(), With<PowerUpIcon>>,
+) {
+    for child in children.iter_descendants(trigger.target()) {
+        if icons.contains(child) {
+            commands.entity(child).try_despawn();
+        }
+    }
+}
+
+fn spawn_power_ups(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut timer: ResMut<PowerUpTimer>,
+    board: Res<Board>,
+    snake: Res<ActivePositions>,
+    power_ups: Query<(), With<PowerUp>>,
+    mut rng: GlobalEntropy<ChaCha8Rng>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() || power_ups.iter().len() >= MAX_POWER_UPS {
+        return;
+    }
+    let candidates = board
+        .gems
+        .iter()
+        .enumerate()
+        .flat_map(|(x, column)| column.iter().enumerate().map(move |(y, gem)| (x, y, gem)))
+        .filter(|(x, y, _)| board.tiles[*x][*y] == Tile::Gem)
+        .filter(|(x, y, _)| !snake.0.contains(&GridPosition { x: *x, y: *y }))
+        .filter_map(|(_, _, gem)| gem.entity)
+        .filter(|entity| !power_ups.contains(*entity))
+        .collect::<Vec<_>>();
+    let (Some(gem), Some(power_up)) = (
+        candidates.choose(&mut **rng),
+        PowerUp::ALL.choose(&mut **rng),
+    ) else {
+        return;
+    };
+    commands.entity(*gem).insert(*power_up);
+}
+
+fn tick_power_ups(time: Res<Time>, mut active: ResMut<ActivePowerUps>) {
+    active
+        .0
+        .retain(|_, timer| !timer.tick(time.delta()).finished());
+}
+
+fn pick_up_power_ups(
+    mut commands: Commands,
+    head: Query<&GridPosition, (With<SnakeHead>, Changed<GridPosition>)>,
+    board: Res<Board>,
+    power_ups: Query<&PowerUp>,
+    mut active: ResMut<ActivePowerUps>,
+    mut pending_shrink: ResMut<PendingShrink>,
+    mut writer: EventWriter<SoundEffect>,
+) {
+    let Ok(head) = head.single() else {
+        return;
+    };
+    let Some(gem) = board.gems[head.x][head.y].entity else {
+        return;
+    };
+    let Ok(power_up) = power_ups.get(gem) else {
+        return;
+    };
+    info!("Picked up {power_up:?} at {}/{}", head.x, head.y);
+    commands.entity(gem).remove::<PowerUp>();
+    writer.write(SoundEffect::Grow);
+    active
+        .0
+        .insert(*power_up, Timer::new(power_up.duration(), TimerMode::Once));
+    match power_up {
+        PowerUp::Detonate => commands.insert_resource(Detonation(head.clone())),
+        PowerUp::Shrink => pending_shrink.0 += SHRINK_PARTS,
+        PowerUp::SlowTime | PowerUp::Ghost => (),
+    }
+}
+
+/// Removes parts from the tail end, the new last two parts become the tail
+#[allow(clippy::too_many_arguments)]
+fn shrink_snake(
+    mut commands: Commands,
+    mut pending_shrink: ResMut<PendingShrink>,
+    skin: Res<SnakeSkin>,
+    growing: Query<(), With<StuckOnce>>,
+    tail: Query<Entity, With<SnakeTail>>,
+    mut parts: Query<(Option<&Trailing>, &mut Sprite, &GridPosition)>,
+    mut positions: ResMut<SnakePositions>,
+    mut length: ResMut<SnakeLength>,
+) -> Result {
+    if !growing.is_empty() {
+        return Ok(());
+    }
+    let mut snake = vec![tail.single()?];
+    while let Some(Trailing(next)) = parts.get(snake[snake.len() - 1])?.0 {
+        snake.push(*next);
+    }
+    let removed = pending_shrink
+        .0
+        .min(snake.len().saturating_sub(SNAKE_START_LENGTH));
+    pending_shrink.0 = 0;
+    if removed == 0 {
+        return Ok(());
+    }
+
+    info!("Shrinking the snake by {removed}");
+    for part in &snake[..removed] {
+        commands.entity(*part).despawn();
+    }
+    let remaining = snake.len() - removed;
+    for (index, part) in snake[removed..removed + 2].iter().enumerate() {
+        let (_, mut sprite, position) = parts.get_mut(*part)?;
+        let (image, layout) = skin.part(index, remaining);
+        sprite.image = image;
+        if let Some(atlas) = sprite.texture_atlas.as_mut() {
+            atlas.layout = layout;
+        }
+        if index == 0 {
+            // The tail is not tracked, so the head can follow it closely
+            positions.0[position.x][position.y].retain(|entity| entity != part);
+            commands
+                .entity(*part)
+                .remove::<SnakeTailInner>()
+                .insert(SnakeTail);
+        } else {
+            commands.entity(*part).insert(SnakeTailInner);
+        }
+    }
+    length.0 = remaining;
+
+    Ok(())
+}
+
+/// Gems around `center` that explode on a detonation, leaving out the ones below the snake
+pub fn detonated(
+    center: &GridPosition,
+    board: &Board,
+    snake: &HashSet<&GridPosition>,
+) -> Vec<GridPosition> {
+    GridPosition::surroundings(&vec![center.clone()])
+        .into_iter()
+        .chain([center.clone()])
+        .filter(|position| board.tiles[position.x][position.y].holds_gem())
+        .filter(|position| !snake.contains(position))
+        .collect()
+}
@@ -0,0 +1,333 @@
+use std::time::Duration;
+
+use bevy::{ecs::system::SystemParam, prelude::*, time::common_conditions::on_timer};
+use bevy_enhanced_input::prelude::Actions;
+use bevy_rand::{
+    global::GlobalEntropy,
+    prelude::{ChaCha8Rng, Entropy},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::{MoveDirection, NextMove, Orientation, Player, TurnQueue},
+    board::{fill_board, Board, Tile},
+    collision::{reset_invulnerability, Invulnerability},
+    daily::DailyChallenge,
+    following::Trailing,
+    gems::{draw_board, GemColors, GemType},
+    generator::DefaultColors,
+    ghost::{reset_clock, RecordingState, SavedRecording},
+    gravity::Gravity,
+    grid::{position_to_transform, Walls, GRID_HEIGHT, GRID_WIDTH},
+    level::{Level, StartingLevel},
+    mode::{reset_time_limit, GameMode, TimeLimit},
+    movement::MovementTimer,
+    player::{
+        spawn_player, GridPosition, GrowthTimer, SnakeHead, SnakeHeadInner, SnakePart,
+        SnakePositions, SnakeTail, SnakeTailInner, StuckOnce,
+    },
+    power_ups::{reset_power_ups, PowerUpState, SavedPowerUps},
+    skins::SnakeSkin,
+    storage,
+    ui::{Explosions, SnakeLength},
+    GamePhase, GameState,
+};
+
+/// Storage key of the run that can be continued
+const SAVE_KEY: &str = "run.ron";
+
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(2);
+
+pub struct SavePlugin;
+
+/// This plugin keeps the current run in storage, so it can be continued after closing the game
+/// The run is saved regularly while the board is settled and removed once it is lost
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::Playing),
+            restore_run
+                .run_if(resource_exists::<ResumedRun>)
+                .after(spawn_player)
+                .after(reset_power_ups)
+                .after(reset_clock)
+                .after(reset_invulnerability)
+                .after(reset_time_limit)
+                .after(fill_board)
+                .before(draw_board),
+        )
+        .add_systems(
+            Update,
+            save_run
+                .run_if(in_state(GamePhase::Playing))
+                .run_if(on_timer(AUTOSAVE_INTERVAL)),
+        )
+        .add_systems(OnEnter(GamePhase::Pause), save_run)
+        .add_systems(
+            Last,
+            save_run
+                .run_if(on_event::<AppExit>)
+                .run_if(in_state(GamePhase::Playing)),
+        )
+        .add_systems(OnEnter(GamePhase::Lost), delete_run);
+    }
+}
+
+/// Everything needed to continue a run
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedRun {
+    gems: [[GemType; GRID_HEIGHT]; GRID_WIDTH],
+    tiles: [[Tile; GRID_HEIGHT]; GRID_WIDTH],
+    /// From the tail to the head, following the `Trailing` chain
+    snake: Vec<SavedPart>,
+    /// Turns the head has queued for its next steps
+    #[serde(default)]
+    turns: TurnQueue,
+    growth: Duration,
+    length: usize,
+    explosions: usize,
+    walls: bool,
+    /// Colours of a run outside of levels
+    #[serde(default)]
+    colors: GemColors,
+    /// Gravity at the time of saving, levels can shift it
+    #[serde(default)]
+    gravity: Gravity,
+    level: Option<Level>,
+    /// Day of the daily challenge this run is an attempt at
+    #[serde(default)]
+    daily: Option<u64>,
+    #[serde(default)]
+    mode: GameMode,
+    /// Time spent against the time limit of the mode
+    #[serde(default)]
+    time: Duration,
+    /// Steps left without self collisions
+    #[serde(default)]
+    invulnerable_steps: u32,
+    #[serde(default)]
+    power_ups: SavedPowerUps,
+    /// Recording of the run for its ghost
+    #[serde(default)]
+    recording: Option<SavedRecording>,
+    rng: Entropy<ChaCha8Rng>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SavedPart {
+    position: GridPosition,
+    orientation: Orientation,
+    next_move: MoveDirection,
+    frame: usize,
+    movement: Duration,
+}
+
+impl SavedRun {
+    pub fn load() -> Result<Self> {
+        Ok(ron::from_str(&storage::read(SAVE_KEY)?)?)
+    }
+
+    /// Set up the resources the next game is started from
+    ///
+    /// Has to happen before entering `GameState::Playing`.
+    pub fn resume(self, commands: &mut Commands) {
+        match &self.level {
+            Some(level) => commands.insert_resource(StartingLevel(level.clone())),
+            None => commands.remove_resource::<StartingLevel>(),
+        }
+        match self.daily {
+            Some(day) => commands.insert_resource(DailyChallenge(day)),
+            None => commands.remove_resource::<DailyChallenge>(),
+        }
+        commands.insert_resource(Walls(self.walls));
+        commands.insert_resource(DefaultColors(self.colors.clone()));
+        commands.insert_resource(self.mode);
+        commands.insert_resource(ResumedRun(self));
+    }
+}
+
+/// The run that the next game continues instead of starting fresh
+#[derive(Resource)]
+pub struct ResumedRun(SavedRun);
+
+pub fn restore_run(
+    mut commands: Commands,
+    run: Res<ResumedRun>,
+    skin: Res<SnakeSkin>,
+    mut rng: GlobalEntropy<ChaCha8Rng>,
+    mut length: ResMut<SnakeLength>,
+    mut time_limit: ResMut<TimeLimit>,
+) {
+    let run = &run.0;
+    info!("Continuing saved run");
+    commands.remove_resource::<ResumedRun>();
+    **rng = run.rng.clone();
+
+    let mut board = Board::default();
+    for x in 0..GRID_WIDTH {
+        for y in 0..GRID_HEIGHT {
+            board.gems[x][y].gem_type = run.gems[x][y].clone();
+            board.tiles[x][y] = run.tiles[x][y].clone();
+        }
+    }
+    commands.insert_resource(board);
+
+    let mut growth = Timer::from_seconds(5., TimerMode::Repeating);
+    growth.tick(run.growth);
+    commands.insert_resource(GrowthTimer(growth));
+    commands.insert_resource(SnakePositions::default());
+    commands.insert_resource(Explosions(run.explosions));
+    commands.insert_resource(run.gravity);
+    length.0 = run.length;
+    time_limit.0.tick(run.time);
+    commands.insert_resource(Invulnerability(run.invulnerable_steps));
+    run.power_ups.restore(&mut commands);
+    if let Some(recording) = &run.recording {
+        recording.restore(&mut commands);
+    }
+
+    // Spawned from the head, so every part can trail the one spawned before it
+    let mut trailing = None;
+    for (index, part) in run.snake.iter().enumerate().rev() {
+        let (image, layout) = skin.part(index, run.snake.len());
+        let mut sprite = Sprite::from_atlas_image(
+            image,
+            TextureAtlas {
+                index: part.frame,
+                layout,
+            },
+        );
+        sprite.flip_x = part.next_move == MoveDirection::Right;
+        let mut transform =
+            Transform::from_translation(position_to_transform(&part.position).extend(1.));
+        transform.rotate_z(part.orientation.z_angle());
+        let mut timer = MovementTimer(Timer::new(Duration::from_millis(100), TimerMode::Repeating));
+        timer.0.tick(part.movement);
+
+        let mut entity = commands.spawn((
+            sprite,
+            transform,
+            part.position.clone(),
+            NextMove(part.next_move),
+            timer,
+            part.orientation,
+            SnakePart,
+        ));
+        if let Some(next) = trailing {
+            entity.insert(Trailing(next));
+        }
+        match index {
+            0 => {
+                entity.insert(SnakeTail);
+            }
+            1 => {
+                entity.insert(SnakeTailInner);
+            }
+            i if i == run.snake.len() - 1 => {
+                entity.insert((SnakeHead, Actions::<Player>::default(), run.turns.clone()));
+            }
+            i if i == run.snake.len() - 2 => {
+                entity.insert(SnakeHeadInner);
+            }
+            _ => (),
+        }
+        trailing = Some(entity.id());
+    }
+}
+
+/// Everything a save records about the run besides the board and the snake
+///
+/// Grouped, so `save_run` stays below the parameter limit of systems as runs gain more state.
+#[derive(SystemParam)]
+struct RunState<'w> {
+    timer: Res<'w, GrowthTimer>,
+    length: Res<'w, SnakeLength>,
+    explosions: Res<'w, Explosions>,
+    walls: Res<'w, Walls>,
+    colors: Res<'w, DefaultColors>,
+    gravity: Res<'w, Gravity>,
+    level: Option<Res<'w, StartingLevel>>,
+    daily: Option<Res<'w, DailyChallenge>>,
+    mode: Res<'w, GameMode>,
+    time_limit: Res<'w, TimeLimit>,
+    invulnerability: Res<'w, Invulnerability>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn save_run(
+    board: Res<Board>,
+    tail: Query<Entity, With<SnakeTail>>,
+    parts: Query<
+        (
+            &GridPosition,
+            &Orientation,
+            &NextMove,
+            &Sprite,
+            &MovementTimer,
+            Option<&Trailing>,
+            Option<&TurnQueue>,
+        ),
+        With<SnakePart>,
+    >,
+    growing: Query<(), With<StuckOnce>>,
+    state: RunState,
+    power_ups: PowerUpState,
+    recording: RecordingState,
+    rng: GlobalEntropy<ChaCha8Rng>,
+) -> Result {
+    // A part that was just added shares its cell with the old tail, wait until it moved out
+    if !growing.is_empty() {
+        return Ok(());
+    }
+
+    let mut snake = vec![];
+    let mut turns = TurnQueue::default();
+    let mut next = Some(tail.single()?);
+    while let Some(entity) = next {
+        let (position, orientation, next_move, sprite, movement, trailing, queue) =
+            parts.get(entity)?;
+        if let Some(queue) = queue {
+            turns = queue.clone();
+        }
+        snake.push(SavedPart {
+            position: position.clone(),
+            orientation: *orientation,
+            next_move: next_move.0,
+            frame: sprite.texture_atlas.as_ref().map_or(0, |atlas| atlas.index),
+            movement: movement.0.elapsed(),
+        });
+        next = trailing.map(|trailing| trailing.0);
+    }
+
+    let run = SavedRun {
+        gems: std::array::from_fn(|x| std::array::from_fn(|y| board.gems[x][y].gem_type.clone())),
+        tiles: board.tiles.clone(),
+        snake,
+        turns,
+        growth: state.timer.0.elapsed(),
+        length: state.length.0,
+        explosions: state.explosions.0,
+        walls: state.walls.0,
+        colors: state.colors.0.clone(),
+        gravity: *state.gravity,
+        level: state.level.map(|level| level.0.clone()),
+        daily: state.daily.map(|daily| daily.0),
+        mode: *state.mode,
+        time: state.time_limit.0.elapsed(),
+        invulnerable_steps: state.invulnerability.0,
+        power_ups: power_ups.save(),
+        recording: recording.save(),
+        rng: (*rng).clone(),
+    };
+    if let Err(error) = storage::write(SAVE_KEY, &ron::to_string(&run)?) {
+        warn!("Failed to save run: {error}");
+    }
+
+    Ok(())
+}
+
+fn delete_run() {
+    if let Err(error) = storage::remove(SAVE_KEY) {
+        warn!("Failed to remove saved run: {error}");
+    }
+}
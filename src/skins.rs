@@ -0,0 +1,275 @@
+use std::io;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    loading::TextureAssets,
+    locale::Localized,
+    mode::{GameMode, HighScores},
+    navigation::Activate,
+    storage,
+};
+
+/// Storage key of the [`SelectedSkin`]
+const SKIN_KEY: &str = "skin.ron";
+
+pub struct SkinsPlugin;
+
+/// This plugin lets the player choose how the snake looks
+/// Skins are listed in a manifest asset, some of them are only unlocked by a high score.
+impl Plugin for SkinsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Skins>()
+            .register_asset_loader(SkinsLoader)
+            .insert_resource(SelectedSkin::load())
+            .init_resource::<SnakeSkin>()
+            .add_observer(activate_skin_button)
+            .add_systems(
+                Update,
+                (
+                    save_skin.run_if(
+                        resource_changed::<SelectedSkin>.and(not(resource_added::<SelectedSkin>)),
+                    ),
+                    (
+                        apply_skin.run_if(
+                            resource_changed::<SelectedSkin>
+                                .or(resource_added::<TextureAssets>)
+                                .or(resource_changed::<HighScores>),
+                        ),
+                        update_skin_text.run_if(resource_changed::<SnakeSkin>),
+                    )
+                        .chain()
+                        .run_if(resource_exists::<TextureAssets>),
+                ),
+            );
+    }
+}
+
+/// Id of the skin the player picked, it only applies while the skin is unlocked
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectedSkin(String);
+
+impl SelectedSkin {
+    fn load() -> Self {
+        storage::read(SKIN_KEY)
+            .and_then(|skin| Ok(ron::from_str(&skin)?))
+            .unwrap_or_default()
+    }
+}
+
+fn save_skin(skin: Res<SelectedSkin>) {
+    let saved = ron::to_string(&*skin)
+        .map_err(BevyError::from)
+        .and_then(|skin| storage::write(SKIN_KEY, &skin));
+    if let Err(error) = saved {
+        warn!("Failed to save skin: {error}");
+    }
+}
+
+/// High score that unlocks a skin
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Unlock {
+    mode: GameMode,
+    score: usize,
+}
+
+impl Unlock {
+    fn reached(&self, high_scores: &HighScores) -> bool {
+        high_scores
+            .best(self.mode)
+            .is_some_and(|best| best >= self.score)
+    }
+}
+
+/// All skins of the manifest, the first one is the default
+#[derive(Asset, TypePath)]
+pub struct Skins(Vec<Skin>);
+
+impl Skins {
+    /// Skins the player can choose from
+    fn unlocked(&self, high_scores: &HighScores) -> Vec<&Skin> {
+        self.0
+            .iter()
+            .filter(|skin| skin.unlock.is_none_or(|unlock| unlock.reached(high_scores)))
+            .collect()
+    }
+}
+
+pub struct Skin {
+    id: String,
+    name: String,
+    unlock: Option<Unlock>,
+    head: Handle<Image>,
+    head2: Handle<Image>,
+    body: Handle<Image>,
+    tail2: Handle<Image>,
+    tail: Handle<Image>,
+}
+
+/// A skin as written in the manifest
+#[derive(Deserialize)]
+struct SkinEntry {
+    id: String,
+    name: String,
+    /// Holds `head.png`, `head2.png`, `body.png`, `tail2.png` and `tail.png`
+    folder: String,
+    #[serde(default)]
+    unlock: Option<Unlock>,
+}
+
+#[derive(Default, TypePath)]
+struct SkinsLoader;
+
+impl AssetLoader for SkinsLoader {
+    type Asset = Skins;
+    type Settings = ();
+    type Error = io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Skins, io::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let entries: Vec<SkinEntry> = ron::de::from_bytes(&bytes)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        // The atlases are dependencies, so the manifest only counts as loaded together with them
+        Ok(Skins(
+            entries
+                .into_iter()
+                .map(|entry| {
+                    let mut atlas =
+                        |part: &str| load_context.load(format!("{}/{part}.png", entry.folder));
+                    Skin {
+                        head: atlas("head"),
+                        head2: atlas("head2"),
+                        body: atlas("body"),
+                        tail2: atlas("tail2"),
+                        tail: atlas("tail"),
+                        id: entry.id,
+                        name: entry.name,
+                        unlock: entry.unlock,
+                    }
+                })
+                .collect(),
+        ))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["skins.ron"]
+    }
+}
+
+/// Atlases of the snake in the skin that is used, all of them share `layout`
+#[derive(Resource, Default)]
+pub struct SnakeSkin {
+    id: String,
+    name: String,
+    pub head: Handle<Image>,
+    pub head2: Handle<Image>,
+    pub body: Handle<Image>,
+    pub tail2: Handle<Image>,
+    pub tail: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+}
+
+impl SnakeSkin {
+    /// Atlas of the part at `index` counted from the tail of a snake with `length` parts
+    pub fn part(&self, index: usize, length: usize) -> (Handle<Image>, Handle<TextureAtlasLayout>) {
+        let image = match index {
+            0 => &self.tail,
+            1 => &self.tail2,
+            i if i == length - 1 => &self.head,
+            i if i == length - 2 => &self.head2,
+            _ => &self.body,
+        };
+
+        (image.clone(), self.layout.clone())
+    }
+}
+
+/// Snakes that are already spawned keep their look, new parts use the skin
+fn apply_skin(
+    selected: Res<SelectedSkin>,
+    textures: Res<TextureAssets>,
+    skins: Res<Assets<Skins>>,
+    high_scores: Res<HighScores>,
+    mut snake_skin: ResMut<SnakeSkin>,
+) {
+    let Some(skins) = skins.get(&textures.skins) else {
+        warn!("The skin manifest is not loaded");
+        return;
+    };
+    let unlocked = skins.unlocked(&high_scores);
+    let Some(skin) = unlocked
+        .iter()
+        .find(|skin| skin.id == selected.0)
+        .or(unlocked.first())
+    else {
+        warn!("There is no unlocked skin");
+        return;
+    };
+    if snake_skin.id != skin.id {
+        info!("Using the {} skin", skin.name);
+    }
+    *snake_skin = SnakeSkin {
+        id: skin.id.clone(),
+        name: skin.name.clone(),
+        head: skin.head.clone(),
+        head2: skin.head2.clone(),
+        body: skin.body.clone(),
+        tail2: skin.tail2.clone(),
+        tail: skin.tail.clone(),
+        layout: textures.snake_layout.clone(),
+    };
+}
+
+/// Menu button cycling through the unlocked skins
+#[derive(Component)]
+pub struct SkinButton;
+
+/// Label of the [`SkinButton`]
+#[derive(Component)]
+pub struct SkinText;
+
+pub fn skin_label(skin: &SnakeSkin) -> Localized {
+    Localized::new("skin").with("name", &skin.name)
+}
+
+fn update_skin_text(skin: Res<SnakeSkin>, mut texts: Query<&mut Localized, With<SkinText>>) {
+    for mut text in &mut texts {
+        text.set_if_neq(skin_label(&skin));
+    }
+}
+
+fn activate_skin_button(
+    trigger: Trigger<Activate>,
+    buttons: Query<(), With<SkinButton>>,
+    textures: Res<TextureAssets>,
+    skins: Res<Assets<Skins>>,
+    high_scores: Res<HighScores>,
+    snake_skin: Res<SnakeSkin>,
+    mut selected: ResMut<SelectedSkin>,
+) {
+    if !buttons.contains(trigger.target()) {
+        return;
+    }
+    let Some(skins) = skins.get(&textures.skins) else {
+        return;
+    };
+    let unlocked = skins.unlocked(&high_scores);
+    let index = unlocked
+        .iter()
+        .position(|skin| skin.id == snake_skin.id)
+        .map_or(0, |index| (index + 1) % unlocked.len());
+    if let Some(next) = unlocked.get(index) {
+        selected.0 = next.id.clone();
+    }
+}
@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+
+/// Directory holding everything written by [`write`]
+#[cfg(not(target_family = "wasm"))]
+const SAVE_DIRECTORY: &str = "saves";
+
+#[cfg(not(target_family = "wasm"))]
+fn path(key: &str) -> std::path::PathBuf {
+    std::path::Path::new(SAVE_DIRECTORY).join(key)
+}
+
+/// Read the value stored under `key` in a previous session
+#[cfg(not(target_family = "wasm"))]
+pub fn read(key: &str) -> Result<String> {
+    Ok(std::fs::read_to_string(path(key))?)
+}
+
+#[cfg(not(target_family = "wasm"))]
+pub fn write(key: &str, value: &str) -> Result {
+    std::fs::create_dir_all(SAVE_DIRECTORY)?;
+    std::fs::write(path(key), value)?;
+
+    Ok(())
+}
+
+#[cfg(not(target_family = "wasm"))]
+pub fn remove(key: &str) -> Result {
+    match std::fs::remove_file(path(key)) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.into()),
+        _ => Ok(()),
+    }
+}
+
+/// In the browser, values live in the local storage of the page
+#[cfg(target_family = "wasm")]
+fn local_storage() -> Result<web_sys::Storage> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| BevyError::from("Local storage is not available"))
+}
+
+#[cfg(target_family = "wasm")]
+pub fn read(key: &str) -> Result<String> {
+    local_storage()?
+        .get_item(key)
+        .map_err(|error| BevyError::from(format!("{error:?}")))?
+        .ok_or_else(|| BevyError::from(format!("Nothing stored for {key}")))
+}
+
+#[cfg(target_family = "wasm")]
+pub fn write(key: &str, value: &str) -> Result {
+    local_storage()?
+        .set_item(key, value)
+        .map_err(|error| BevyError::from(format!("{error:?}")))
+}
+
+#[cfg(target_family = "wasm")]
+pub fn remove(key: &str) -> Result {
+    local_storage()?
+        .remove_item(key)
+        .map_err(|error| BevyError::from(format!("{error:?}")))
+}
@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+
+use crate::locale::Localized;
+
+// Shared look of the menus and the HUD
+
+pub const TEXT_COLOR: Color = Color::linear_rgb(0.9, 0.9, 0.9);
+/// Dims the game behind menus that are shown while playing
+pub const OVERLAY_BACKGROUND: Color = Color::srgba(0.886, 0.91, 0.941, 0.2);
+pub const PANEL_BACKGROUND: Color = Color::linear_rgba(0.05, 0.05, 0.05, 0.6);
+pub const BAR_BACKGROUND: Color = Color::linear_rgba(0.15, 0.15, 0.15, 0.8);
+pub const BAR_FILL: Color = Color::linear_rgb(0.3, 0.7, 0.35);
+/// Outline of the button that keyboard and gamepad input acts on
+pub const FOCUS_OUTLINE: Color = Color::linear_rgb(0.95, 0.75, 0.2);
+
+pub const TITLE_FONT_SIZE: f32 = 40.0;
+pub const BUTTON_FONT_SIZE: f32 = 25.0;
+pub const BODY_FONT_SIZE: f32 = 20.0;
+pub const HUD_FONT_SIZE: f32 = 18.0;
+pub const SMALL_FONT_SIZE: f32 = 15.0;
+
+pub const CORNER_RADIUS: f32 = 10.0;
+pub const SPACING: f32 = 12.0;
+
+#[derive(Component)]
+pub struct ButtonColors {
+    pub normal: Color,
+    pub hovered: Color,
+}
+
+impl Default for ButtonColors {
+    fn default() -> Self {
+        ButtonColors {
+            normal: Color::linear_rgba(0.15, 0.15, 0.15, 0.5),
+            hovered: Color::linear_rgb(0.25, 0.25, 0.25),
+        }
+    }
+}
+
+pub fn text(content: impl Into<String>, font_size: f32) -> impl Bundle {
+    (
+        Text::new(content),
+        TextFont {
+            font_size,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+    )
+}
+
+/// Text that follows the selected language
+pub fn label(localized: Localized, font_size: f32) -> impl Bundle {
+    (localized, text("", font_size))
+}
+
+/// A menu button of the given size, its label is added as a child
+pub fn button(width: f32, height: f32) -> impl Bundle {
+    let colors = ButtonColors::default();
+    (
+        Button,
+        Node {
+            width: Val::Px(width),
+            max_width: Val::Percent(90.0),
+            height: Val::Px(height),
+            margin: UiRect::top(Val::Px(SPACING)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        BackgroundColor(colors.normal),
+        BorderRadius::all(Val::Px(CORNER_RADIUS)),
+        colors,
+    )
+}
+
+/// Translucent box grouping a few lines of text
+pub fn panel() -> impl Bundle {
+    (
+        Node {
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::axes(Val::Px(SPACING), Val::Px(SPACING / 2.)),
+            row_gap: Val::Px(4.0),
+            ..default()
+        },
+        BackgroundColor(PANEL_BACKGROUND),
+        BorderRadius::all(Val::Px(CORNER_RADIUS)),
+    )
+}
+
+/// Horizontal bar, its fill is the child with `marker` and grows with its `Node::width` percentage
+pub fn progress_bar(width: f32, marker: impl Component) -> impl Bundle {
+    (
+        Node {
+            width: Val::Px(width),
+            height: Val::Px(10.0),
+            ..default()
+        },
+        BackgroundColor(BAR_BACKGROUND),
+        BorderRadius::all(Val::Px(5.0)),
+        children![(
+            Node {
+                width: Val::Percent(0.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            BackgroundColor(BAR_FILL),
+            BorderRadius::all(Val::Px(5.0)),
+            marker,
+        )],
+    )
+}
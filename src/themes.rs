@@ -0,0 +1,332 @@
+use std::io;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    grid::FloorTile, level::StartingLevel, loading::ThemeAssets, locale::Localized,
+    navigation::Activate, storage, GameState,
+};
+
+/// Storage key of the [`SelectedTheme`]
+const THEME_KEY: &str = "theme.ron";
+
+/// Size of the background layers, large enough to cover the window at every zoom of the camera
+const BACKGROUND_SIZE: f32 = 4096.;
+
+pub struct ThemesPlugin;
+
+/// This plugin applies the visual theme: floor tiles, background, clear colour, gems and music
+/// Themes are listed in a manifest asset. A level can ask for its own theme, otherwise the one
+/// selected in the settings is used.
+impl Plugin for ThemesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Themes>()
+            .register_asset_loader(ThemesLoader)
+            .insert_resource(SelectedTheme::load())
+            .init_resource::<ActiveTheme>()
+            .add_observer(activate_theme_button)
+            .add_systems(
+                Update,
+                (
+                    save_theme.run_if(
+                        resource_changed::<SelectedTheme>.and(not(resource_added::<SelectedTheme>)),
+                    ),
+                    (
+                        apply_theme,
+                        (restyle_floor, update_theme_text).run_if(resource_changed::<ActiveTheme>),
+                        scroll_background,
+                    )
+                        .chain()
+                        .run_if(resource_exists::<ThemeAssets>),
+                ),
+            );
+    }
+}
+
+/// Id of the theme picked in the settings
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectedTheme(String);
+
+impl SelectedTheme {
+    fn load() -> Self {
+        storage::read(THEME_KEY)
+            .and_then(|theme| Ok(ron::from_str(&theme)?))
+            .unwrap_or_default()
+    }
+}
+
+fn save_theme(theme: Res<SelectedTheme>) {
+    let saved = ron::to_string(&*theme)
+        .map_err(BevyError::from)
+        .and_then(|theme| storage::write(THEME_KEY, &theme));
+    if let Err(error) = saved {
+        warn!("Failed to save theme: {error}");
+    }
+}
+
+/// All themes of the manifest, the first one is the default
+#[derive(Asset, TypePath)]
+pub struct Themes(Vec<Theme>);
+
+impl Themes {
+    fn get(&self, id: &str) -> Option<&Theme> {
+        self.0.iter().find(|theme| theme.id == id)
+    }
+}
+
+#[derive(Clone)]
+pub struct Theme {
+    id: String,
+    name: String,
+    tile: Handle<Image>,
+    clear_color: Color,
+    layers: Vec<(Handle<Image>, f32)>,
+    /// One image per gem type, in the order of [`crate::gems::GemType::ALL`]
+    gems: Option<Vec<Handle<Image>>>,
+    music: Handle<AudioSource>,
+}
+
+/// A theme as written in the manifest
+#[derive(Deserialize)]
+struct ThemeEntry {
+    id: String,
+    name: String,
+    tile: String,
+    /// Linear RGB
+    clear_color: (f32, f32, f32),
+    /// Drawn from back to front
+    #[serde(default)]
+    layers: Vec<LayerEntry>,
+    /// Folder with `gem1.png` to `gem7.png`, the standard gems are used without it
+    #[serde(default)]
+    gems: Option<String>,
+    music: String,
+}
+
+#[derive(Deserialize)]
+struct LayerEntry {
+    image: String,
+    /// Horizontal scroll speed in pixels per second, further layers should move slower
+    #[serde(default)]
+    speed: f32,
+}
+
+#[derive(Default, TypePath)]
+struct ThemesLoader;
+
+impl AssetLoader for ThemesLoader {
+    type Asset = Themes;
+    type Settings = ();
+    type Error = io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Themes, io::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let entries: Vec<ThemeEntry> = ron::de::from_bytes(&bytes)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        // Everything a theme uses is a dependency, so switching themes never shows missing images
+        Ok(Themes(
+            entries
+                .into_iter()
+                .map(|entry| Theme {
+                    tile: load_context.load(entry.tile),
+                    clear_color: Color::linear_rgb(
+                        entry.clear_color.0,
+                        entry.clear_color.1,
+                        entry.clear_color.2,
+                    ),
+                    layers: entry
+                        .layers
+                        .into_iter()
+                        .map(|layer| (load_context.load(layer.image), layer.speed))
+                        .collect(),
+                    gems: entry.gems.map(|folder| {
+                        (1..=7)
+                            .map(|index| load_context.load(format!("{folder}/gem{index}.png")))
+                            .collect()
+                    }),
+                    music: load_context.load(entry.music),
+                    id: entry.id,
+                    name: entry.name,
+                })
+                .collect(),
+        ))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["themes.ron"]
+    }
+}
+
+/// The theme that is currently shown
+#[derive(Resource, Default)]
+pub struct ActiveTheme(Option<Theme>);
+
+impl ActiveTheme {
+    pub fn tile(&self) -> Handle<Image> {
+        self.0
+            .as_ref()
+            .map(|theme| theme.tile.clone())
+            .unwrap_or_default()
+    }
+
+    /// Image of the gem at `index`, if the theme has its own gem set
+    pub fn gem(&self, index: usize) -> Option<Handle<Image>> {
+        self.0
+            .as_ref()
+            .and_then(|theme| theme.gems.as_ref())
+            .and_then(|gems| gems.get(index))
+            .cloned()
+    }
+
+    fn id(&self) -> Option<&str> {
+        self.0.as_ref().map(|theme| theme.id.as_str())
+    }
+}
+
+#[derive(Component)]
+struct BackgroundLayer {
+    speed: f32,
+    width: f32,
+}
+
+#[derive(Component)]
+struct BackgroundMusic;
+
+/// Switches to the theme of the level or the selected one as soon as it differs from the active one
+#[allow(clippy::too_many_arguments)]
+fn apply_theme(
+    mut commands: Commands,
+    assets: Res<ThemeAssets>,
+    all_themes: Res<Assets<Themes>>,
+    images: Res<Assets<Image>>,
+    selected: Res<SelectedTheme>,
+    level: Option<Res<StartingLevel>>,
+    state: Res<State<GameState>>,
+    mut active: ResMut<ActiveTheme>,
+    previous: Query<Entity, Or<(With<BackgroundLayer>, With<BackgroundMusic>)>>,
+) {
+    let Some(themes) = all_themes.get(&assets.themes) else {
+        return;
+    };
+    // The theme of a level only applies while it is played
+    let wanted = level
+        .as_ref()
+        .filter(|_| *state.get() == GameState::Playing)
+        .and_then(|level| level.0.theme.as_deref())
+        .and_then(|id| themes.get(id))
+        .or_else(|| themes.get(&selected.0))
+        .or(themes.0.first());
+    let Some(theme) = wanted else {
+        return;
+    };
+    if active.id() == Some(theme.id.as_str()) {
+        return;
+    }
+
+    info!("Using the {} theme", theme.name);
+    for entity in &previous {
+        commands.entity(entity).despawn();
+    }
+    commands.insert_resource(ClearColor(theme.clear_color));
+    for (index, (image, speed)) in theme.layers.iter().enumerate() {
+        let width = images
+            .get(image)
+            .map_or(BACKGROUND_SIZE, |image| image.width() as f32);
+        commands.spawn((
+            Sprite {
+                image: image.clone(),
+                custom_size: Some(Vec2::splat(BACKGROUND_SIZE)),
+                image_mode: SpriteImageMode::Tiled {
+                    tile_x: true,
+                    tile_y: true,
+                    stretch_value: 1.,
+                },
+                ..default()
+            },
+            Transform::from_xyz(0., 0., -10. + index as f32),
+            BackgroundLayer {
+                speed: *speed,
+                width,
+            },
+        ));
+    }
+    commands.spawn((
+        AudioPlayer::new(theme.music.clone()),
+        PlaybackSettings::LOOP,
+        BackgroundMusic,
+    ));
+    active.0 = Some(theme.clone());
+}
+
+/// Moves the layers by at most one image width, which looks the same as scrolling endlessly
+fn scroll_background(time: Res<Time>, mut layers: Query<(&BackgroundLayer, &mut Transform)>) {
+    for (layer, mut transform) in &mut layers {
+        transform.translation.x = -(time.elapsed_secs() * layer.speed).rem_euclid(layer.width);
+    }
+}
+
+fn restyle_floor(theme: Res<ActiveTheme>, mut tiles: Query<&mut Sprite, With<FloorTile>>) {
+    for mut sprite in &mut tiles {
+        sprite.image = theme.tile();
+    }
+}
+
+/// Menu button cycling through the themes
+#[derive(Component)]
+pub struct ThemeButton;
+
+/// Label of the [`ThemeButton`]
+#[derive(Component)]
+pub struct ThemeText;
+
+pub fn theme_label(theme: &ActiveTheme) -> Localized {
+    Localized::new("visual-theme").with(
+        "name",
+        theme
+            .0
+            .as_ref()
+            .map(|theme| theme.name.as_str())
+            .unwrap_or_default(),
+    )
+}
+
+fn update_theme_text(theme: Res<ActiveTheme>, mut texts: Query<&mut Localized, With<ThemeText>>) {
+    for mut text in &mut texts {
+        text.set_if_neq(theme_label(&theme));
+    }
+}
+
+fn activate_theme_button(
+    trigger: Trigger<Activate>,
+    buttons: Query<(), With<ThemeButton>>,
+    assets: Res<ThemeAssets>,
+    all_themes: Res<Assets<Themes>>,
+    active: Res<ActiveTheme>,
+    mut selected: ResMut<SelectedTheme>,
+) {
+    if !buttons.contains(trigger.target()) {
+        return;
+    }
+    let Some(themes) = all_themes.get(&assets.themes) else {
+        return;
+    };
+    let index = themes
+        .0
+        .iter()
+        .position(|theme| Some(theme.id.as_str()) == active.id())
+        .map_or(0, |index| (index + 1) % themes.0.len());
+    if let Some(next) = themes.0.get(index) {
+        selected.0 = next.id.clone();
+    }
+}
@@ -1,6 +1,13 @@
 use bevy::prelude::*;
 
-use crate::{player::GrowthTimer, GameState};
+use crate::{
+    locale::Localized,
+    mode::{GameMode, HighScores, TimeLimit},
+    player::GrowthTimer,
+    power_ups::{ActivePowerUps, PowerUp},
+    theme::{label, panel, progress_bar, HUD_FONT_SIZE, SPACING},
+    GameState,
+};
 
 pub struct GameUiPlugin;
 
@@ -11,10 +18,19 @@ impl Plugin for GameUiPlugin {
             .init_resource::<ExplosionsTotal>()
             .init_resource::<BiggestChainReaction>()
             .init_resource::<MaxSnakeLength>()
-            .add_systems(OnExit(GameState::Menu), setup)
+            .add_systems(OnEnter(GameState::Playing), setup)
+            .add_systems(OnExit(GameState::Playing), cleanup)
             .add_systems(
                 Update,
-                (update_max_length, (update_game_ui, update_other_game_ui))
+                (
+                    update_max_length,
+                    (
+                        update_game_ui,
+                        update_other_game_ui,
+                        update_mode_ui,
+                        update_power_up_ui,
+                    ),
+                )
                     .chain()
                     .run_if(in_state(GameState::Playing)),
             );
@@ -57,97 +73,176 @@ struct CurrentLengthText;
 #[derive(Component)]
 struct MaxLengthText;
 
+/// Fill of the bar showing the progress towards the next growth
 #[derive(Component)]
-struct NextGrowthText;
-
-fn setup(mut commands: Commands, timer: Res<GrowthTimer>) {
-    commands.spawn((
-        Text::new("Snake length: 0"),
-        Node {
-            position_type: PositionType::Absolute,
-            top: Val::Px(12.0),
-            left: Val::Px(12.0),
-            ..default()
-        },
-        CurrentLengthText,
-    ));
-    commands.spawn((
-        Text::new("Record length: 0"),
-        Node {
-            position_type: PositionType::Absolute,
-            top: Val::Px(12.0),
-            left: Val::Px(270.0),
-            ..default()
-        },
-        MaxLengthText,
-    ));
-    commands.spawn((
-        Text::new("Gems destroyed: 0"),
-        Node {
-            position_type: PositionType::Absolute,
-            top: Val::Px(35.0),
-            left: Val::Px(12.0),
-            ..default()
-        },
-        ExplosionsText,
-    ));
-    commands.spawn((
-        Text::new("Total destroyed: 0"),
-        Node {
-            position_type: PositionType::Absolute,
-            top: Val::Px(35.0),
-            left: Val::Px(270.0),
-            ..default()
-        },
-        ExplosionsTotalText,
-    ));
-    commands.spawn((
-        Text::new(format!("Next growth: {}s", timer.0.remaining_secs().ceil())),
-        Node {
-            position_type: PositionType::Absolute,
-            top: Val::Px(12.0),
-            right: Val::Px(12.0),
-            ..default()
-        },
-        NextGrowthText,
-    ));
-    commands.spawn((
-        Text::new("Largest chain: 0"),
-        Node {
-            position_type: PositionType::Absolute,
-            top: Val::Px(35.0),
-            right: Val::Px(12.0),
-            ..default()
-        },
-        BiggestChainReactionText,
-    ));
+struct GrowthBar;
+
+#[derive(Component)]
+struct TimeLeftText;
+
+#[derive(Component)]
+struct BestScoreText;
+
+/// Only shown while a power-up is active
+#[derive(Component)]
+struct PowerUpsPanel;
+
+#[derive(Component)]
+struct PowerUpRow(PowerUp);
+
+/// Fill of the bar showing the remaining duration of a power-up
+#[derive(Component)]
+struct PowerUpBar(PowerUp);
+
+#[derive(Component)]
+struct GameUi;
+
+/// Root of the HUD, the board is placed below it
+#[derive(Component)]
+pub struct Hud;
+
+/// Every mode shows the length and destroyed gems, the other slots depend on what matters in the mode
+///
+/// The panels wrap onto more rows when the window gets too narrow.
+fn setup(mut commands: Commands, mode: Res<GameMode>, high_scores: Res<HighScores>) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                flex_direction: FlexDirection::Row,
+                flex_wrap: FlexWrap::Wrap,
+                justify_content: JustifyContent::SpaceBetween,
+                align_items: AlignItems::FlexStart,
+                padding: UiRect::all(Val::Px(SPACING)),
+                row_gap: Val::Px(SPACING / 2.),
+                column_gap: Val::Px(SPACING),
+                ..default()
+            },
+            GameUi,
+            Hud,
+        ))
+        .with_children(|hud| {
+            hud.spawn(panel()).with_children(|panel| {
+                panel.spawn((
+                    label(
+                        Localized::new("snake-length").with("length", 0),
+                        HUD_FONT_SIZE,
+                    ),
+                    CurrentLengthText,
+                ));
+                panel.spawn((
+                    label(
+                        Localized::new("gems-destroyed").with("count", 0),
+                        HUD_FONT_SIZE,
+                    ),
+                    ExplosionsText,
+                ));
+            });
+            hud.spawn(panel()).with_children(|panel| {
+                if *mode == GameMode::Endless {
+                    panel.spawn((
+                        label(
+                            Localized::new("record-length").with("length", 0),
+                            HUD_FONT_SIZE,
+                        ),
+                        MaxLengthText,
+                    ));
+                } else {
+                    panel.spawn((
+                        label(best_score_label(*mode, &high_scores), HUD_FONT_SIZE),
+                        BestScoreText,
+                    ));
+                }
+                panel.spawn((
+                    label(
+                        Localized::new("total-destroyed").with("count", 0),
+                        HUD_FONT_SIZE,
+                    ),
+                    ExplosionsTotalText,
+                ));
+            });
+            hud.spawn(panel()).with_children(|panel| {
+                if *mode == GameMode::TimeAttack {
+                    panel.spawn((
+                        label(
+                            Localized::new("time-left").with("time", "2:00"),
+                            HUD_FONT_SIZE,
+                        ),
+                        TimeLeftText,
+                    ));
+                } else {
+                    panel
+                        .spawn(Node {
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(8.0),
+                            ..default()
+                        })
+                        .with_children(|row| {
+                            row.spawn(label(Localized::new("next-growth"), HUD_FONT_SIZE));
+                            row.spawn(progress_bar(120.0, GrowthBar));
+                        });
+                }
+                panel.spawn((
+                    label(
+                        Localized::new("largest-chain").with("count", 0),
+                        HUD_FONT_SIZE,
+                    ),
+                    BiggestChainReactionText,
+                ));
+            });
+            hud.spawn((panel(), PowerUpsPanel)).with_children(|panel| {
+                for power_up in PowerUp::ALL {
+                    panel
+                        .spawn((
+                            Node {
+                                display: Display::None,
+                                align_items: AlignItems::Center,
+                                column_gap: Val::Px(8.0),
+                                ..default()
+                            },
+                            PowerUpRow(power_up),
+                        ))
+                        .with_children(|row| {
+                            row.spawn(label(Localized::new(power_up.key()), HUD_FONT_SIZE));
+                            row.spawn(progress_bar(80.0, PowerUpBar(power_up)));
+                        });
+                }
+            });
+        });
+}
+
+fn cleanup(mut commands: Commands, ui: Query<Entity, With<GameUi>>) {
+    for entity in ui {
+        commands.entity(entity).despawn();
+    }
 }
 
 fn update_game_ui(
-    mut length_text: Query<
-        &mut Text,
-        (
-            With<CurrentLengthText>,
-            Without<MaxLengthText>,
-            Without<NextGrowthText>,
-        ),
-    >,
-    mut max_length_text: Query<&mut Text, (With<MaxLengthText>, Without<NextGrowthText>)>,
-    mut next_growth_text: Query<&mut Text, With<NextGrowthText>>,
+    mut length_text: Query<&mut Localized, (With<CurrentLengthText>, Without<MaxLengthText>)>,
+    mut max_length_text: Query<&mut Localized, With<MaxLengthText>>,
+    mut growth_bar: Query<&mut Node, With<GrowthBar>>,
     length: Res<SnakeLength>,
     max_length: Res<MaxSnakeLength>,
     timer: Res<GrowthTimer>,
 ) -> Result {
-    **length_text.single_mut()? = format!("Snake length: {}", length.0);
-    **max_length_text.single_mut()? = format!("Record length: {}", max_length.0);
-    **next_growth_text.single_mut()? = format!("Next growth: {}s", timer.0.remaining_secs().ceil());
+    length_text
+        .single_mut()?
+        .set_if_neq(Localized::new("snake-length").with("length", length.0));
+    // Not every mode shows these
+    for mut text in &mut max_length_text {
+        text.set_if_neq(Localized::new("record-length").with("length", max_length.0));
+    }
+    for mut node in &mut growth_bar {
+        node.width = Val::Percent(timer.0.fraction() * 100.);
+    }
 
     Ok(())
 }
 
 fn update_other_game_ui(
     mut explosions_text: Query<
-        &mut Text,
+        &mut Localized,
         (
             With<ExplosionsText>,
             Without<ExplosionsTotalText>,
@@ -155,18 +250,79 @@ fn update_other_game_ui(
         ),
     >,
     mut explosions_total_text: Query<
-        &mut Text,
+        &mut Localized,
         (With<ExplosionsTotalText>, Without<BiggestChainReactionText>),
     >,
-    mut biggest_chain_reaction_text: Query<&mut Text, With<BiggestChainReactionText>>,
+    mut biggest_chain_reaction_text: Query<&mut Localized, With<BiggestChainReactionText>>,
     explosions: Res<Explosions>,
     explosions_total: Res<ExplosionsTotal>,
     biggest_chain_reaction: Res<BiggestChainReaction>,
 ) -> Result {
-    **explosions_text.single_mut()? = format!("Gems destroyed: {}", explosions.0);
-    **explosions_total_text.single_mut()? = format!("Total destroyed: {}", explosions_total.0);
-    **biggest_chain_reaction_text.single_mut()? =
-        format!("Largest chain: {}", biggest_chain_reaction.0);
+    explosions_text
+        .single_mut()?
+        .set_if_neq(Localized::new("gems-destroyed").with("count", explosions.0));
+    explosions_total_text
+        .single_mut()?
+        .set_if_neq(Localized::new("total-destroyed").with("count", explosions_total.0));
+    biggest_chain_reaction_text
+        .single_mut()?
+        .set_if_neq(Localized::new("largest-chain").with("count", biggest_chain_reaction.0));
 
     Ok(())
 }
+
+fn best_score_label(mode: GameMode, high_scores: &HighScores) -> Localized {
+    Localized::new(mode.best_score_key()).with("score", high_scores.best(mode).unwrap_or_default())
+}
+
+fn update_mode_ui(
+    mut time_left_text: Query<&mut Localized, (With<TimeLeftText>, Without<BestScoreText>)>,
+    mut best_score_text: Query<&mut Localized, With<BestScoreText>>,
+    mode: Res<GameMode>,
+    time_limit: Res<TimeLimit>,
+    high_scores: Res<HighScores>,
+) {
+    for mut text in &mut time_left_text {
+        let seconds = time_limit.0.remaining_secs().ceil() as u32;
+        text.set_if_neq(
+            Localized::new("time-left")
+                .with("time", format!("{}:{:02}", seconds / 60, seconds % 60)),
+        );
+    }
+    if high_scores.is_changed() {
+        for mut text in &mut best_score_text {
+            *text = best_score_label(*mode, &high_scores);
+        }
+    }
+}
+
+fn update_power_up_ui(
+    mut panel: Query<&mut Node, With<PowerUpsPanel>>,
+    mut rows: Query<(&PowerUpRow, &mut Node), Without<PowerUpsPanel>>,
+    mut bars: Query<(&PowerUpBar, &mut Node), (Without<PowerUpRow>, Without<PowerUpsPanel>)>,
+    active: Res<ActivePowerUps>,
+) {
+    let shown = |power_up| {
+        if active.is_active(power_up) {
+            Display::Flex
+        } else {
+            Display::None
+        }
+    };
+    for mut node in &mut panel {
+        node.display = if PowerUp::ALL
+            .iter()
+            .any(|power_up| active.is_active(*power_up))
+        {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+    for (row, mut node) in &mut rows {
+        node.display = shown(row.0);
+    }
+    for (bar, mut node) in &mut bars {
+        node.width = Val::Percent(active.remaining(bar.0).unwrap_or_default() * 100.);
+    }
+}